@@ -0,0 +1,162 @@
+//! Compares the allocations and latency of capturing and decoding a synthetic DHT11 transmission
+//! by collecting every sample into a `Vec<Level>` first, with the streaming capture of
+//! `DHT11Controller`.
+//!
+//! Run with `cargo bench --bench capture`.
+
+use dht11_gpio::decode;
+use dht11_gpio::encode::{self, Encoder};
+use dht11_gpio::{Bias, DHT11Controller, DHT11Pin, Level, PinMode, Sensor};
+use embedded_hal::delay::DelayNs;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::convert::Infallible;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Allocator counting the allocations and allocated bytes.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of reads measured per method.
+const READS: u32 = 200;
+
+/// Time the line is sampled after the last level change by the `Vec<Level>` capture.
+const IDLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Pin replaying pre-sampled levels, one per microsecond, without allocating.
+struct SampledPin<'a> {
+    levels: &'a [Level],
+    position: usize,
+    clock: u64,
+    mode: PinMode,
+}
+
+impl DHT11Pin for SampledPin<'_> {
+    type Error = Infallible;
+
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error> {
+        if mode == PinMode::Input {
+            self.position = 0;
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    fn set_bias(&mut self, _bias: Bias) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Level, Self::Error> {
+        self.clock += 1;
+        if self.mode == PinMode::Output {
+            return Ok(Level::Low);
+        }
+        let level = self
+            .levels
+            .get(self.position)
+            .copied()
+            .unwrap_or(Level::High);
+        self.position += 1;
+        Ok(level)
+    }
+
+    fn timestamp(&mut self) -> Option<Duration> {
+        Some(Duration::from_micros(self.clock))
+    }
+}
+
+/// Delay skipping the start signal.
+struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// Runs `read` `READS` times, printing the allocations and mean latency per read.
+fn measure<F: FnMut()>(name: &str, mut read: F) {
+    // Warm up, e.g. allocating reused buffers
+    read();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..READS {
+        read();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<28} {:>8.1} allocations {:>12.0} bytes {:>10.1} µs per read",
+        name,
+        (ALLOCATIONS.load(Ordering::Relaxed) - allocations) as f64 / READS as f64,
+        (ALLOCATED.load(Ordering::Relaxed) - allocated) as f64 / READS as f64,
+        elapsed.as_secs_f64() * 1e6 / READS as f64,
+    );
+}
+
+fn main() {
+    let bytes = encode::with_checksum([45, 0, 21, 3]);
+    let mut levels = Encoder::new().levels(&bytes, Duration::from_micros(1));
+    // The line stays idle after the transmission
+    let idle = IDLE_TIMEOUT.as_micros() as usize;
+    levels.extend(std::iter::repeat_n(Level::High, idle));
+
+    measure("Vec<Level> + decode_levels", || {
+        // Collecting every sample until the line was stable for the timeout, then decoding
+        let mut data: Vec<Level> = Vec::new();
+        for &level in &levels {
+            data.push(black_box(level));
+        }
+        let frame = decode::decode_levels(&data).unwrap();
+        black_box(frame.to_result().unwrap());
+    });
+
+    let pin = SampledPin {
+        levels: &levels,
+        position: 0,
+        clock: 0,
+        mode: PinMode::Input,
+    };
+    // The replayed reads follow each other immediately
+    let mut controller =
+        DHT11Controller::with_delay(pin, NoDelay).with_min_interval(Duration::ZERO);
+    measure("DHT11Controller (streaming)", || {
+        black_box(controller.read_sensor_data().unwrap());
+    });
+
+    let mut controller = controller.with_capture(true);
+    measure("DHT11Controller (capture)", || {
+        black_box(controller.read_sensor_data().unwrap());
+    });
+}
@@ -0,0 +1,68 @@
+use dht11_gpio::sigrok::{self, Stats};
+use std::env;
+use std::process;
+
+/// Formats pulse length statistics in microseconds.
+fn format_stats(stats: Option<Stats>) -> String {
+    match stats {
+        Some(stats) => format!(
+            "{:.1} µs (min {}, max {}, n {})",
+            stats.mean, stats.min, stats.max, stats.count
+        ),
+        None => "-".to_string(),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("usage: {} <capture.sr|capture.csv> [channel]", args[0]);
+        process::exit(2);
+    }
+    let path = &args[1];
+    let channel = args.get(2).map(String::as_str);
+
+    let trace = if path.ends_with(".sr") {
+        sigrok::load_sr(path, channel)
+    } else {
+        sigrok::load_csv(path, channel)
+    };
+    let trace = match trace {
+        Ok(trace) => trace,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+
+    let reports = trace.frames();
+    println!("{} reads found", reports.len());
+    for (index, report) in reports.iter().enumerate() {
+        println!();
+        println!("read {} at {:.6} s", index, report.start.as_secs_f64());
+        match &report.frame {
+            Ok(frame) => {
+                println!("bytes: {:02x?}", frame.bytes);
+                match frame.to_result() {
+                    Ok(data) => println!(
+                        "temperature: {} °C, humidity: {} %",
+                        data.temperature, data.humidity
+                    ),
+                    Err(err) => println!("error: {}", err),
+                }
+            }
+            Err(err) => println!("error: {}", err),
+        }
+
+        let timing = &report.timing;
+        if let Some(start_signal) = timing.start_signal {
+            println!("start signal: {} µs", start_signal.as_micros());
+        }
+        if let (Some(low), Some(high)) = (timing.response_low, timing.response_high) {
+            println!("response: {} µs low, {} µs high", low, high);
+        }
+        println!("bit low: {}", format_stats(timing.bit_low));
+        println!("0 high: {}", format_stats(timing.zero_high));
+        println!("1 high: {}", format_stats(timing.one_high));
+    }
+}
@@ -0,0 +1,473 @@
+use crate::decode::ThresholdStrategy;
+use crate::model::Model;
+use crate::pin::{Bias, DHT11Pin};
+#[cfg(feature = "std")]
+use crate::StdDelay;
+use crate::{DHT11Controller, IntervalPolicy, NO_RESPONSE_TIMEOUT, START_HIGH, TIMEOUT_DURATION};
+use core::fmt;
+use core::time::Duration;
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "rppal")]
+use rppal::gpio::{Gpio, Mode};
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// Duration of a complete transmission with the longest bits: the 80µs low and high response,
+/// 40 bits of a 50µs low and a 70µs high and the trailing 50µs low. A shorter deadline would cut
+/// off every capture.
+const TRANSMISSION_DURATION: Duration = Duration::from_micros(80 + 80 + 40 * (50 + 70) + 50);
+
+/// Maximum duration the line is driven high before the start signal. The line idles high, so a
+/// longer one only delays the read, and it has to fit the microsecond delay.
+const MAX_START_HIGH: Duration = Duration::from_secs(1);
+
+/// Maximum time the sensor waits before responding to the start signal (datasheet: 20-40µs).
+const MAX_RESPONSE_DELAY: Duration = Duration::from_micros(40);
+
+/// Builder of a `DHT11Controller`, configuring the protocol timings and validating them against
+/// the datasheet limits of the sensor model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DHT11ControllerBuilder {
+    model: Model,
+    start_high: Duration,
+    start_signal: Option<Duration>,
+    bias: Bias,
+    deadline: Duration,
+    no_response_timeout: Duration,
+    min_interval: Option<Duration>,
+    interval_policy: IntervalPolicy,
+    strategy: ThresholdStrategy,
+}
+
+impl Default for DHT11ControllerBuilder {
+    fn default() -> Self {
+        DHT11ControllerBuilder::new()
+    }
+}
+
+impl DHT11ControllerBuilder {
+    /// Creates a builder with the same defaults as the constructors of `DHT11Controller`.
+    pub fn new() -> DHT11ControllerBuilder {
+        DHT11ControllerBuilder {
+            model: Model::DHT11,
+            start_high: START_HIGH,
+            start_signal: None,
+            bias: Bias::PullUp,
+            deadline: Duration::from_millis(TIMEOUT_DURATION as u64),
+            no_response_timeout: NO_RESPONSE_TIMEOUT,
+            min_interval: None,
+            interval_policy: IntervalPolicy::Block,
+            strategy: ThresholdStrategy::Auto,
+        }
+    }
+
+    /// Sets the sensor model connected to the pin, by default a DHT11.
+    pub fn with_model(mut self, model: Model) -> DHT11ControllerBuilder {
+        self.model = model;
+        self
+    }
+
+    /// Sets the duration the line is driven high before the start signal, by default 50ms. It can
+    /// be at most 1s.
+    pub fn with_start_high(mut self, start_high: Duration) -> DHT11ControllerBuilder {
+        self.start_high = start_high;
+        self
+    }
+
+    /// Sets the duration the line is pulled low for the start signal, by default the one of the
+    /// model (20ms for the DHT11, 1ms for the others). It has to be within the datasheet limits
+    /// of the model, see `Model::min_start_signal()` and `Model::max_start_signal()`.
+    pub fn with_start_signal(mut self, start_signal: Duration) -> DHT11ControllerBuilder {
+        self.start_signal = Some(start_signal);
+        self
+    }
+
+    /// Sets the built-in resistor configured while receiving the response, by default the
+    /// pull-up. `Bias::Off` can be used with an external pull-up resistor, the pull-down is
+    /// rejected as the line has to idle high.
+    pub fn with_bias(mut self, bias: Bias) -> DHT11ControllerBuilder {
+        self.bias = bias;
+        self
+    }
+
+    /// Sets the maximum duration of a capture, by default 200ms. It has to be long enough for a
+    /// complete transmission of about 5ms.
+    pub fn with_deadline(mut self, deadline: Duration) -> DHT11ControllerBuilder {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Sets the maximum duration waiting for the sensor to respond to the start signal, by
+    /// default 10ms. It has to be at least the 40µs the sensor may wait before responding.
+    pub fn with_no_response_timeout(mut self, timeout: Duration) -> DHT11ControllerBuilder {
+        self.no_response_timeout = timeout;
+        self
+    }
+
+    /// Sets the minimum interval between two reads, by default the one of the model. It can not
+    /// be shorter than the one of the datasheet, see `Model::min_interval()`.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> DHT11ControllerBuilder {
+        self.min_interval = Some(min_interval);
+        self
+    }
+
+    /// Sets the behavior when reading before the minimum interval between two reads has passed,
+    /// by default the read blocks until the sensor is ready.
+    pub fn with_interval_policy(mut self, policy: IntervalPolicy) -> DHT11ControllerBuilder {
+        self.interval_policy = policy;
+        self
+    }
+
+    /// Sets the strategy used to classify the pull-up lengths into bits, by default the absolute
+    /// thresholds with a fallback to the relative midpoint.
+    pub fn with_threshold_strategy(
+        mut self,
+        strategy: ThresholdStrategy,
+    ) -> DHT11ControllerBuilder {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Validates the configuration against the datasheet limits of the sensor model.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.start_high > MAX_START_HIGH {
+            return Err(ConfigError::StartHighTooLong {
+                max: MAX_START_HIGH,
+            });
+        }
+        if let Some(start_signal) = self.start_signal {
+            if start_signal < self.model.min_start_signal() {
+                return Err(ConfigError::StartSignalTooShort {
+                    min: self.model.min_start_signal(),
+                });
+            }
+            if start_signal > self.model.max_start_signal() {
+                return Err(ConfigError::StartSignalTooLong {
+                    max: self.model.max_start_signal(),
+                });
+            }
+        }
+        if self.bias == Bias::PullDown {
+            return Err(ConfigError::InvalidBias(self.bias));
+        }
+        if self.deadline < TRANSMISSION_DURATION {
+            return Err(ConfigError::DeadlineTooShort {
+                min: TRANSMISSION_DURATION,
+            });
+        }
+        if self.no_response_timeout < MAX_RESPONSE_DELAY {
+            return Err(ConfigError::NoResponseTimeoutTooShort {
+                min: MAX_RESPONSE_DELAY,
+            });
+        }
+        match self.min_interval {
+            Some(min_interval) if min_interval < self.model.min_interval() => {
+                Err(ConfigError::MinIntervalTooShort {
+                    min: self.model.min_interval(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates the configuration and builds a controller using a pin backend and a delay
+    /// provider.
+    pub fn build<P: DHT11Pin, D: DelayNs>(
+        self,
+        dht_pin: P,
+        delay: D,
+    ) -> Result<DHT11Controller<P, D>, ConfigError> {
+        self.validate()?;
+        let mut controller = DHT11Controller::with_delay(dht_pin, delay)
+            .with_model(self.model)
+            .with_deadline(self.deadline)
+            .with_no_response_timeout(self.no_response_timeout)
+            .with_interval_policy(self.interval_policy);
+        controller.min_interval = self.min_interval;
+        controller.start_high = self.start_high;
+        controller.start_signal = self.start_signal;
+        controller.bias = self.bias;
+        controller.strategy = self.strategy;
+        Ok(controller)
+    }
+
+    /// Validates the configuration and builds a controller using an already configured pin
+    /// backend, e.g. an rppal `IoPin` of a shared `Gpio`.
+    #[cfg(feature = "std")]
+    pub fn build_from_pin<P: DHT11Pin>(
+        self,
+        dht_pin: P,
+    ) -> Result<DHT11Controller<P>, ConfigError> {
+        self.build(dht_pin, StdDelay)
+    }
+
+    /// Validates the configuration and builds a controller using a pin of an already opened GPIO
+    /// peripheral, the pin is only acquired if the configuration is valid.
+    #[cfg(feature = "rppal")]
+    pub fn build_from_gpio(self, gpio: &Gpio, dht_pin: u8) -> Result<DHT11Controller, BuildError> {
+        self.validate()?;
+        let dht_pin = gpio.get(dht_pin)?.into_io(Mode::Output);
+        Ok(self.build_from_pin(dht_pin)?)
+    }
+}
+
+/// Enum representing the settings of a `DHT11ControllerBuilder` outside of the datasheet limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The line is driven high before the start signal for longer than needed
+    StartHighTooLong {
+        /// Maximum duration the line is driven high
+        max: Duration,
+    },
+    /// The start signal is shorter than the sensor model reacts to
+    StartSignalTooShort {
+        /// Minimum duration of the start signal
+        min: Duration,
+    },
+    /// The start signal is longer than the sensor model waits for
+    StartSignalTooLong {
+        /// Maximum duration of the start signal
+        max: Duration,
+    },
+    /// The bias would pull the line away from its idle high level
+    InvalidBias(Bias),
+    /// The deadline is shorter than a complete transmission
+    DeadlineTooShort {
+        /// Duration of a complete transmission
+        min: Duration,
+    },
+    /// The no-response timeout is shorter than the sensor may wait before responding
+    NoResponseTimeoutTooShort {
+        /// Maximum response delay of the sensor
+        min: Duration,
+    },
+    /// The minimum interval is shorter than the one of the sensor model
+    MinIntervalTooShort {
+        /// Minimum interval of the sensor model
+        min: Duration,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartHighTooLong { max } => {
+                write!(f, "Start high too long (at most {} ms)", max.as_millis())
+            }
+            Self::StartSignalTooShort { min } => {
+                write!(
+                    f,
+                    "Start signal too short (at least {} µs)",
+                    min.as_micros()
+                )
+            }
+            Self::StartSignalTooLong { max } => {
+                write!(f, "Start signal too long (at most {} µs)", max.as_micros())
+            }
+            Self::InvalidBias(bias) => write!(f, "Invalid bias {:?}, the line idles high", bias),
+            Self::DeadlineTooShort { min } => {
+                write!(f, "Deadline too short (at least {} µs)", min.as_micros())
+            }
+            Self::NoResponseTimeoutTooShort { min } => write!(
+                f,
+                "No-response timeout too short (at least {} µs)",
+                min.as_micros()
+            ),
+            Self::MinIntervalTooShort { min } => {
+                write!(
+                    f,
+                    "Minimum interval too short (at least {} ms)",
+                    min.as_millis()
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ConfigError {}
+
+/// Enum representing the errors building a controller from a GPIO pin number with
+/// `DHT11ControllerBuilder::build_from_gpio()`.
+#[cfg(feature = "rppal")]
+#[derive(Debug)]
+pub enum BuildError {
+    /// The configuration is outside of the datasheet limits
+    Config(ConfigError),
+    /// The GPIO pin could not be acquired
+    Gpio(rppal::gpio::Error),
+}
+
+#[cfg(feature = "rppal")]
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(err) => write!(f, "Invalid configuration: {}", err),
+            Self::Gpio(err) => write!(f, "GPIO error: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Config(err) => Some(err),
+            Self::Gpio(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl From<ConfigError> for BuildError {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl From<rppal::gpio::Error> for BuildError {
+    fn from(err: rppal::gpio::Error) -> Self {
+        Self::Gpio(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_defaults() {
+        for model in [
+            Model::DHT11,
+            Model::DHT11Legacy,
+            Model::DHT22,
+            Model::DHT21,
+            Model::DHT12,
+        ] {
+            let builder = DHT11ControllerBuilder::new().with_model(model);
+            assert_eq!(builder.validate(), Ok(()));
+            let builder = builder
+                .with_start_signal(model.start_signal())
+                .with_min_interval(model.min_interval());
+            assert_eq!(builder.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_start_high() {
+        let builder = DHT11ControllerBuilder::new().with_start_high(Duration::from_secs(2));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::StartHighTooLong {
+                max: Duration::from_secs(1)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_start_signal() {
+        let builder = DHT11ControllerBuilder::new().with_start_signal(Duration::from_millis(10));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::StartSignalTooShort {
+                min: Duration::from_millis(18)
+            })
+        );
+        let builder = DHT11ControllerBuilder::new()
+            .with_model(Model::DHT22)
+            .with_start_signal(Duration::from_millis(25));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::StartSignalTooLong {
+                max: Duration::from_millis(20)
+            })
+        );
+        let builder = DHT11ControllerBuilder::new()
+            .with_model(Model::DHT21)
+            .with_start_signal(Duration::from_micros(600));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::StartSignalTooShort {
+                min: Duration::from_micros(800)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bias() {
+        let builder = DHT11ControllerBuilder::new().with_bias(Bias::PullDown);
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::InvalidBias(Bias::PullDown))
+        );
+        assert_eq!(
+            DHT11ControllerBuilder::new()
+                .with_bias(Bias::Off)
+                .validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_timeouts() {
+        let builder = DHT11ControllerBuilder::new().with_deadline(Duration::from_millis(5));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::DeadlineTooShort {
+                min: Duration::from_micros(5010)
+            })
+        );
+        let builder =
+            DHT11ControllerBuilder::new().with_no_response_timeout(Duration::from_micros(39));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::NoResponseTimeoutTooShort {
+                min: Duration::from_micros(40)
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_min_interval() {
+        let builder = DHT11ControllerBuilder::new()
+            .with_model(Model::DHT22)
+            .with_min_interval(Duration::from_secs(1));
+        assert_eq!(
+            builder.validate(),
+            Err(ConfigError::MinIntervalTooShort {
+                min: Duration::from_secs(2)
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn builds_controller() {
+        use crate::sim::SimulatedPin;
+
+        let pin = SimulatedPin::new([45, 0, 21, 3, 69]);
+        // Start high, start signal and the response, ended right after the last bit
+        let end = Duration::from_millis(30) + pin.response_duration();
+        let delay = pin.delay();
+        let mut controller = DHT11ControllerBuilder::new()
+            .with_start_high(Duration::from_millis(5))
+            .with_start_signal(Duration::from_millis(25))
+            .with_threshold_strategy(ThresholdStrategy::Relative)
+            .build(pin, delay)
+            .unwrap();
+
+        let reading = controller.read().unwrap();
+        assert_eq!(reading.result.temperature, 21.3);
+        assert_eq!(
+            controller.last_bit_decoding(),
+            Some(crate::BitDecoding::Relative)
+        );
+        let now = controller.into_pin().now();
+        assert!(
+            now >= end && now <= end + Duration::from_micros(5),
+            "ended at {:?}",
+            now
+        );
+    }
+}
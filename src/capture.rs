@@ -0,0 +1,629 @@
+//! Raw captures of the sensor response, retained by `DHT11Controller::with_capture()` to debug
+//! failed reads, recorded with a `Recorder` and replayed through `read_sensor_data()` with a
+//! `ReplayPin`.
+//!
+//! # File format
+//!
+//! Captures are stored as UTF-8 text, one `<key> <value>` entry per line. Empty lines and lines
+//! starting with `#` are ignored, the files written start with a `# dht11_gpio capture` comment.
+//!
+//! The metadata comes first, all entries are optional:
+//!
+//! - `version 1`: version of the format.
+//! - `pin <number>`: GPIO pin the sensor is connected to.
+//! - `backend <name>`: pin backend used for the read, e.g. `rppal` or `sysfs`.
+//! - `timestamp <seconds>`: start of the read, in seconds since the Unix epoch.
+//! - `sample_period <microseconds>`: time between two samples of the pin, fractional for pins
+//!   sampled faster than once per microsecond.
+//! - `start_signal <microseconds>`: length of the start signal driven before the capture.
+//!
+//! Followed by the unit of the payload, `unit us` or `unit samples`, which is required, and the
+//! payload in one of two forms, which can be mixed:
+//!
+//! - Edges, a `high <length>` or `low <length>` line per pulse, the level and how long it was held
+//!   until the next edge.
+//! - Sampled levels, `levels <digits>` lines of `0` (low) and `1` (high) per sample, which are
+//!   only allowed with `unit samples`.
+//!
+//! ```text
+//! # dht11_gpio capture
+//! version 1
+//! pin 4
+//! backend rppal
+//! timestamp 1760000000.250000
+//! unit us
+//! high 29
+//! low 80
+//! high 80
+//! low 50
+//! high 27
+//! ...
+//! ```
+
+use crate::decode::{DHT11Frame, PulseParser, PulseUnit};
+use crate::pin::{Bias, DHT11Pin, PinMode};
+use crate::sim::SimulatedDelay;
+use crate::{DHT11Controller, DHT11Error, DHT11Result, Level, Sensor};
+use core::convert::Infallible;
+use embedded_hal::delay::DelayNs;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// First line of a capture file.
+const HEADER: &str = "# dht11_gpio capture";
+
+/// Version of the capture file format written.
+const VERSION: u32 = 1;
+
+/// Extension of the capture files written by `Recorder::save()`.
+const EXTENSION: &str = "dht11";
+
+/// Information about a capture, stored along with its payload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// GPIO pin the sensor is connected to.
+    pub pin: Option<u32>,
+    /// Pin backend used for the read.
+    pub backend: Option<String>,
+    /// Start of the read.
+    pub timestamp: Option<SystemTime>,
+    /// Time between two samples of the pin. The controller records the mean over the read, or
+    /// the delay between the samples if it had no clock to measure it.
+    pub sample_period: Option<Duration>,
+    /// Length of the start signal driven before the capture.
+    pub start_signal: Option<Duration>,
+}
+
+/// Pulses received from the sensor during a single read, each a level and how long it was held.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// Information about the capture.
+    pub metadata: Metadata,
+    /// Unit of the pulse lengths.
+    pub unit: PulseUnit,
+    /// Levels and their lengths, in the order they were received.
+    pub pulses: Vec<(Level, u32)>,
+}
+
+impl Capture {
+    /// Creates an empty capture with lengths in the specified unit.
+    pub fn new(unit: PulseUnit) -> Capture {
+        Capture {
+            metadata: Metadata::default(),
+            unit,
+            pulses: Vec::new(),
+        }
+    }
+
+    /// Creates a capture of sampled levels, run-length encoding them into pulses.
+    pub fn from_levels(levels: &[Level]) -> Capture {
+        let mut capture = Capture::new(PulseUnit::Samples);
+        for &level in levels {
+            capture.push_sample(level);
+        }
+        capture
+    }
+
+    /// Appends a sample, extending the last pulse if the level did not change.
+    fn push_sample(&mut self, level: Level) {
+        match self.pulses.last_mut() {
+            Some((last, length)) if *last == level => *length += 1,
+            _ => self.pulses.push((level, 1)),
+        }
+    }
+
+    /// Length of a pulse as a duration, samples are converted using the sample period of the
+    /// metadata, or 1µs if unknown.
+    pub fn duration(&self, length: u32) -> Duration {
+        match self.unit {
+            PulseUnit::Microseconds => Duration::from_micros(length as u64),
+            PulseUnit::Samples => {
+                self.metadata
+                    .sample_period
+                    .unwrap_or(Duration::from_micros(1))
+                    * length
+            }
+        }
+    }
+
+    /// Feeds the pulses into a new parser, e.g. to inspect the evidence of a failed read.
+    pub fn parser(&self) -> PulseParser {
+        let mut parser = PulseParser::new(self.unit);
+        for &(level, length) in &self.pulses {
+            parser.feed_pulse(level, length);
+        }
+        parser
+    }
+
+    /// Decodes the captured pulses into a frame.
+    pub fn decode(&self) -> Result<DHT11Frame, DHT11Error> {
+        self.parser().finish()
+    }
+
+    /// Writes the capture in the format described in the module documentation, the payload is
+    /// written as edges.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "version {}", VERSION)?;
+        if let Some(pin) = self.metadata.pin {
+            writeln!(writer, "pin {}", pin)?;
+        }
+        if let Some(backend) = &self.metadata.backend {
+            writeln!(writer, "backend {}", backend)?;
+        }
+        if let Some(timestamp) = self.metadata.timestamp {
+            let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            writeln!(
+                writer,
+                "timestamp {}.{:06}",
+                timestamp.as_secs(),
+                timestamp.subsec_micros()
+            )?;
+        }
+        if let Some(sample_period) = self.metadata.sample_period {
+            writeln!(
+                writer,
+                "sample_period {}",
+                sample_period.as_nanos() as f64 / 1000.0
+            )?;
+        }
+        if let Some(start_signal) = self.metadata.start_signal {
+            writeln!(writer, "start_signal {}", start_signal.as_micros())?;
+        }
+
+        let unit = match self.unit {
+            PulseUnit::Microseconds => "us",
+            PulseUnit::Samples => "samples",
+        };
+        writeln!(writer, "unit {}", unit)?;
+        for &(level, length) in &self.pulses {
+            let level = match level {
+                Level::High => "high",
+                Level::Low => "low",
+            };
+            writeln!(writer, "{} {}", level, length)?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a capture in the format described in the module documentation.
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Capture> {
+        let mut metadata = Metadata::default();
+        let mut capture: Option<Capture> = None;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid_data(format!("invalid line: {}", line)))?;
+            let value = value.trim();
+            match (key, capture.as_mut()) {
+                ("version", None) => {
+                    if parse::<u32>(key, value)? > VERSION {
+                        return Err(invalid_data(format!("unsupported version: {}", value)));
+                    }
+                }
+                ("pin", None) => metadata.pin = Some(parse(key, value)?),
+                ("backend", None) => metadata.backend = Some(value.to_string()),
+                ("timestamp", None) => {
+                    let seconds: f64 = parse(key, value)?;
+                    metadata.timestamp = Duration::try_from_secs_f64(seconds)
+                        .ok()
+                        .and_then(|timestamp| UNIX_EPOCH.checked_add(timestamp));
+                }
+                ("sample_period", None) => {
+                    let micros: f64 = parse(key, value)?;
+                    if !micros.is_finite() || micros < 0.0 {
+                        return Err(invalid_data(format!("invalid {}: {}", key, value)));
+                    }
+                    metadata.sample_period =
+                        Some(Duration::from_nanos((micros * 1000.0).round() as u64));
+                }
+                ("start_signal", None) => {
+                    metadata.start_signal = Some(Duration::from_micros(parse(key, value)?))
+                }
+                ("unit", None) => {
+                    let unit = match value {
+                        "us" => PulseUnit::Microseconds,
+                        "samples" => PulseUnit::Samples,
+                        unit => return Err(invalid_data(format!("unknown unit: {}", unit))),
+                    };
+                    capture = Some(Capture {
+                        metadata: metadata.clone(),
+                        unit,
+                        pulses: Vec::new(),
+                    });
+                }
+                ("high" | "low", Some(capture)) => {
+                    let level = if key == "high" {
+                        Level::High
+                    } else {
+                        Level::Low
+                    };
+                    capture.pulses.push((level, parse(key, value)?));
+                }
+                ("levels", Some(capture)) if capture.unit == PulseUnit::Samples => {
+                    for digit in value.chars() {
+                        let level = match digit {
+                            '0' => Level::Low,
+                            '1' => Level::High,
+                            _ => return Err(invalid_data(format!("invalid level: {}", digit))),
+                        };
+                        capture.push_sample(level);
+                    }
+                }
+                _ => return Err(invalid_data(format!("unexpected line: {}", line))),
+            }
+        }
+        capture.ok_or_else(|| invalid_data("missing unit".to_string()))
+    }
+
+    /// Dumps the capture to a file, see the module documentation for the format.
+    pub fn save<Q: AsRef<Path>>(&self, path: Q) -> io::Result<()> {
+        self.write_to(BufWriter::new(File::create(path)?))
+    }
+
+    /// Loads a capture from a file.
+    pub fn load<Q: AsRef<Path>>(path: Q) -> io::Result<Capture> {
+        Capture::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Loads all captures (`.dht11` files) from a directory, sorted by file name.
+pub fn load_dir<Q: AsRef<Path>>(dir: Q) -> io::Result<Vec<Capture>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == EXTENSION)
+        })
+        .collect();
+    paths.sort();
+    paths.iter().map(Capture::load).collect()
+}
+
+/// Parses the value of an entry.
+fn parse<T: core::str::FromStr>(key: &str, value: &str) -> io::Result<T> {
+    value
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid {}: {}", key, value)))
+}
+
+/// Creates an `InvalidData` error for a malformed capture.
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Wrapper around a `DHT11Controller` recording the capture of every read, e.g. to build a
+/// regression corpus of real-world captures.
+pub struct Recorder<P, D> {
+    controller: DHT11Controller<P, D>,
+    /// Metadata added to the captures.
+    metadata: Metadata,
+    /// Captures recorded since the last save.
+    captures: Vec<Capture>,
+    /// Number of the next capture file.
+    saved: usize,
+}
+
+impl<P: DHT11Pin, D: DelayNs> Recorder<P, D> {
+    /// Creates a recorder, enabling the capture retention of the controller.
+    pub fn new(controller: DHT11Controller<P, D>) -> Recorder<P, D> {
+        Recorder {
+            controller: controller.with_capture(true),
+            metadata: Metadata::default(),
+            captures: Vec::new(),
+            saved: 0,
+        }
+    }
+
+    /// Sets the GPIO pin stored in the metadata of the captures.
+    pub fn with_pin(mut self, pin: u32) -> Recorder<P, D> {
+        self.metadata.pin = Some(pin);
+        self
+    }
+
+    /// Sets the backend name stored in the metadata of the captures.
+    pub fn with_backend(mut self, backend: &str) -> Recorder<P, D> {
+        self.metadata.backend = Some(backend.to_string());
+        self
+    }
+
+    /// Returns the captures recorded since the last save.
+    pub fn captures(&self) -> &[Capture] {
+        &self.captures
+    }
+
+    /// Saves the captures recorded since the last save into a directory, as numbered
+    /// `capture-<number>.dht11` files, and clears them. Existing files are not overwritten, the
+    /// numbering continues after them.
+    pub fn save<Q: AsRef<Path>>(&mut self, dir: Q) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for capture in self.captures.drain(..) {
+            let path = loop {
+                let path = dir
+                    .as_ref()
+                    .join(format!("capture-{:04}.{}", self.saved, EXTENSION));
+                self.saved += 1;
+                if !path.exists() {
+                    break path;
+                }
+            };
+            capture.save(&path)?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Consumes the recorder, returning the controller.
+    pub fn into_controller(self) -> DHT11Controller<P, D> {
+        self.controller.with_capture(false)
+    }
+}
+
+impl<P: DHT11Pin, D: DelayNs> Sensor<DHT11Result, DHT11Error<P::Error>> for Recorder<P, D> {
+    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error<P::Error>> {
+        let result = self.controller.read_sensor_data();
+        if let Some(capture) = self.controller.last_capture() {
+            let mut capture = capture.clone();
+            capture.metadata.pin = self.metadata.pin.or(capture.metadata.pin);
+            capture.metadata.backend = self
+                .metadata
+                .backend
+                .clone()
+                .or(capture.metadata.backend.take());
+            self.captures.push(capture);
+        }
+        result
+    }
+}
+
+/// Pin backend replaying recorded captures, implementing `DHT11Pin`.
+///
+/// Like the `SimulatedPin`, the pin runs on a virtual clock advanced by every read and by the
+/// paired delay. Every time the pin is switched to input mode the next capture is played, once
+/// all are played the line stays high.
+pub struct ReplayPin {
+    /// Virtual clock shared with the delays created by `delay()`.
+    clock: Rc<Cell<Duration>>,
+    /// Time advanced by every read of the pin.
+    sample_period: Duration,
+    /// Captures not yet played.
+    captures: VecDeque<Capture>,
+    mode: PinMode,
+    /// Level driven in output mode.
+    output: Level,
+    /// Pulses of the capture currently played, with the time it started.
+    playing: Option<(Duration, Vec<(Level, Duration)>)>,
+}
+
+impl ReplayPin {
+    /// Creates a pin replaying the captures in order, sampled every microsecond.
+    pub fn new<I: IntoIterator<Item = Capture>>(captures: I) -> ReplayPin {
+        ReplayPin {
+            clock: Rc::new(Cell::new(Duration::ZERO)),
+            sample_period: Duration::from_micros(1),
+            captures: captures.into_iter().collect(),
+            mode: PinMode::Input,
+            output: Level::High,
+            playing: None,
+        }
+    }
+
+    /// Creates a delay advancing the virtual clock of this pin, to be passed to
+    /// `DHT11Controller::with_delay()`.
+    pub fn delay(&self) -> SimulatedDelay {
+        SimulatedDelay::new(self.clock.clone())
+    }
+
+    /// Sets the time advanced by every read of the pin.
+    pub fn with_sample_period(mut self, sample_period: Duration) -> ReplayPin {
+        self.sample_period = sample_period;
+        self
+    }
+
+    /// Number of captures not yet played.
+    pub fn remaining(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// Level of the line at the current time while not driven.
+    fn line_level(&mut self) -> Level {
+        if let Some((start, pulses)) = &self.playing {
+            let mut end = *start;
+            for &(level, duration) in pulses {
+                end += duration;
+                if self.clock.get() < end {
+                    return level;
+                }
+            }
+            self.playing = None;
+        }
+        Level::High
+    }
+}
+
+impl DHT11Pin for ReplayPin {
+    type Error = Infallible;
+
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error> {
+        if self.mode == PinMode::Output && mode == PinMode::Input {
+            self.playing = self.captures.pop_front().map(|capture| {
+                let pulses = capture
+                    .pulses
+                    .iter()
+                    .map(|&(level, length)| (level, capture.duration(length)))
+                    .collect();
+                (self.clock.get(), pulses)
+            });
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    fn set_bias(&mut self, _bias: Bias) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.output = Level::High;
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.output = Level::Low;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Level, Self::Error> {
+        self.clock.set(self.clock.get() + self.sample_period);
+        Ok(match self.mode {
+            PinMode::Output => self.output,
+            PinMode::Input => self.line_level(),
+        })
+    }
+
+    fn timestamp(&mut self) -> Option<Duration> {
+        Some(self.clock.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{Fault, SimulatedPin};
+    use crate::test_util::TempDir;
+    use crate::ErrorKind;
+
+    /// 45% humidity and 21.3°C with a valid checksum.
+    const BYTES: [u8; 5] = [45, 0, 21, 3, 69];
+
+    /// Records a read of a simulated sensor and saves it into the directory.
+    fn record(dir: &Path, fault: Option<Fault>) -> Result<DHT11Result, DHT11Error> {
+        let mut pin = SimulatedPin::new(BYTES);
+        pin.set_fault(fault);
+        let delay = pin.delay();
+        let mut recorder = Recorder::new(DHT11Controller::with_delay(pin, delay))
+            .with_pin(4)
+            .with_backend("sim");
+        let result = recorder.read_sensor_data();
+        recorder.save(dir).unwrap();
+        result
+    }
+
+    #[test]
+    fn replays_recorded_reads() {
+        let dir = TempDir::new("replay");
+        let reading = record(dir.path(), None).unwrap();
+        let err = record(dir.path(), Some(Fault::CorruptedChecksum)).unwrap_err();
+
+        // The numbering of the second recorder continues after the existing file
+        assert_eq!(dir.read("capture-0001.dht11").lines().next(), Some(HEADER));
+        let captures = load_dir(dir.path()).unwrap();
+        assert_eq!(captures.len(), 2);
+        for capture in &captures {
+            assert_eq!(capture.metadata.pin, Some(4));
+            assert_eq!(capture.metadata.backend.as_deref(), Some("sim"));
+        }
+
+        let pin = ReplayPin::new(captures);
+        let delay = pin.delay();
+        let mut controller = DHT11Controller::with_delay(pin, delay);
+        assert_eq!(controller.read_sensor_data().unwrap(), reading);
+        let replayed = controller.read_sensor_data().unwrap_err();
+        assert_eq!(replayed.kind(), ErrorKind::InvalidChecksum);
+        assert_eq!(replayed.evidence(), err.evidence());
+        assert_eq!(controller.into_pin().remaining(), 0);
+    }
+
+    #[test]
+    fn reads_levels() {
+        let capture = Capture::read_from(
+            "# comment\nsample_period 2\nunit samples\nlevels 0011\n\nlevels 10\nhigh 3\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(capture.unit, PulseUnit::Samples);
+        assert_eq!(
+            capture.pulses,
+            [
+                (Level::Low, 2),
+                (Level::High, 3),
+                (Level::Low, 1),
+                (Level::High, 3)
+            ]
+        );
+        assert_eq!(capture.duration(3), Duration::from_micros(6));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for text in [
+            "",
+            "pin 4\n",
+            "version 2\nunit us\n",
+            "pin four\nunit us\n",
+            "unit ms\n",
+            "high 3\nunit us\n",
+            "unit us\nhigh\n",
+            "unit us\nhigh -3\n",
+            "unit us\nlevels 01\n",
+            "unit samples\nlevels 012\n",
+            "unit us\npin 4\n",
+            "unit us\nrising 3\n",
+        ] {
+            let err = Capture::read_from(text.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn records_sample_period() {
+        let pin =
+            SimulatedPin::new([45, 0, 21, 3, 69]).with_sample_period(Duration::from_nanos(250));
+        let delay = pin.delay();
+        let mut recorder = Recorder::new(DHT11Controller::with_delay(pin, delay));
+        recorder.read_sensor_data().unwrap();
+
+        let capture = &recorder.captures()[0];
+        assert_eq!(capture.unit, PulseUnit::Microseconds);
+        assert_eq!(
+            capture.metadata.sample_period,
+            Some(Duration::from_nanos(250))
+        );
+
+        let mut file = Vec::new();
+        capture.write_to(&mut file).unwrap();
+        let text = String::from_utf8(file).unwrap();
+        assert!(text.contains("sample_period 0.25\n"), "{}", text);
+        let loaded = Capture::read_from(text.as_bytes()).unwrap();
+        assert_eq!(
+            loaded.metadata.sample_period,
+            capture.metadata.sample_period
+        );
+        assert_eq!(loaded.pulses, capture.pulses);
+    }
+
+    #[test]
+    fn parses_sample_period() {
+        let read = |sample_period: &str| {
+            Capture::read_from(
+                format!("sample_period {}\nunit samples\n", sample_period).as_bytes(),
+            )
+            .map(|capture| capture.metadata.sample_period)
+        };
+        assert_eq!(read("2").unwrap(), Some(Duration::from_micros(2)));
+        assert_eq!(read("0.333").unwrap(), Some(Duration::from_nanos(333)));
+        assert!(read("-1").is_err());
+        assert!(read("inf").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+use crate::decode::{BitDecoding, PulseParser, PulseUnit, DATA_BITS};
+use crate::pin::Level;
+use crate::{decode_reading, CaptureTimeouts, DHT11Error, DHT11Result, Model, Sensor};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Subset of the GPIO character device v2 uAPI, see `include/uapi/linux/gpio.h`.
+
+const GPIO_V2_LINES_MAX: usize = 64;
+const GPIO_MAX_NAME_SIZE: usize = 32;
+const GPIO_V2_LINE_NUM_ATTRS_MAX: usize = 10;
+
+const GPIO_V2_LINE_FLAG_INPUT: u64 = 1 << 2;
+const GPIO_V2_LINE_FLAG_OUTPUT: u64 = 1 << 3;
+const GPIO_V2_LINE_FLAG_EDGE_RISING: u64 = 1 << 4;
+const GPIO_V2_LINE_FLAG_EDGE_FALLING: u64 = 1 << 5;
+const GPIO_V2_LINE_FLAG_BIAS_PULL_UP: u64 = 1 << 8;
+
+const GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES: u32 = 2;
+
+const GPIO_V2_LINE_EVENT_RISING_EDGE: u32 = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LineAttribute {
+    id: u32,
+    padding: u32,
+    /// Union of `flags`, `values` and `debounce_period_us` in the kernel struct.
+    value: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LineConfigAttribute {
+    attr: LineAttribute,
+    mask: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LineConfig {
+    flags: u64,
+    num_attrs: u32,
+    padding: [u32; 5],
+    attrs: [LineConfigAttribute; GPIO_V2_LINE_NUM_ATTRS_MAX],
+}
+
+#[repr(C)]
+struct LineRequest {
+    offsets: [u32; GPIO_V2_LINES_MAX],
+    consumer: [u8; GPIO_MAX_NAME_SIZE],
+    config: LineConfig,
+    num_lines: u32,
+    event_buffer_size: u32,
+    padding: [u32; 5],
+    fd: i32,
+}
+
+#[repr(C)]
+struct LineValues {
+    bits: u64,
+    mask: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LineEvent {
+    timestamp_ns: u64,
+    id: u32,
+    offset: u32,
+    seqno: u32,
+    line_seqno: u32,
+    padding: [u32; 6],
+}
+
+const _: () = assert!(mem::size_of::<LineConfig>() == 272);
+const _: () = assert!(mem::size_of::<LineRequest>() == 592);
+const _: () = assert!(mem::size_of::<LineEvent>() == 48);
+
+/// Equivalent of the `_IOWR` macro for the GPIO ioctl type `0xB4`.
+const fn iowr(nr: u64, size: usize) -> u64 {
+    (3 << 30) | ((size as u64) << 16) | (0xB4 << 8) | nr
+}
+
+const GPIO_V2_GET_LINE_IOCTL: u64 = iowr(0x07, mem::size_of::<LineRequest>());
+const GPIO_V2_LINE_SET_CONFIG_IOCTL: u64 = iowr(0x0D, mem::size_of::<LineConfig>());
+const GPIO_V2_LINE_SET_VALUES_IOCTL: u64 = iowr(0x0F, mem::size_of::<LineValues>());
+
+/// Number of edge events the kernel buffers for the line. A transmission is about 84 edges, by
+/// default only 16 are buffered, so edges would be lost if the process is not scheduled in time.
+const EVENT_BUFFER_SIZE: u32 = 128;
+const _: () = assert!(EVENT_BUFFER_SIZE as usize >= 2 * DATA_BITS + 8);
+
+/// Consumer label shown for the requested line, e.g. by `gpioinfo`.
+const CONSUMER: &[u8] = b"dht11_gpio";
+
+/// Calls an ioctl, converting a failure into the last OS error.
+fn ioctl<T>(fd: i32, request: u64, arg: &mut T) -> io::Result<()> {
+    // SAFETY: `arg` points to a `repr(C)` struct matching the layout the request expects.
+    let ret = unsafe { libc::ioctl(fd, request as _, arg as *mut T) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Line configuration with the given flags and an optional initial output value.
+fn line_config(flags: u64, output: Option<Level>) -> LineConfig {
+    // SAFETY: the struct only consists of integers, for which all zeroes is valid.
+    let mut config: LineConfig = unsafe { mem::zeroed() };
+    config.flags = flags;
+    if let Some(level) = output {
+        config.num_attrs = 1;
+        config.attrs[0] = LineConfigAttribute {
+            attr: LineAttribute {
+                id: GPIO_V2_LINE_ATTR_ID_OUTPUT_VALUES,
+                padding: 0,
+                value: (level == Level::High) as u64,
+            },
+            mask: 1,
+        };
+    }
+    config
+}
+
+/// Struct representing a DHT11 sensor connected to a line of a Linux GPIO character device.
+///
+/// Instead of polling the pin, the sensor response is captured as edge events using the GPIO v2
+/// uAPI, timestamped by the kernel, so the decoding does not depend on the CPU speed or
+/// scheduling. The backend can be tested on any Linux machine with the `gpio-sim` kernel module.
+pub struct CdevController {
+    /// File descriptor of the requested line.
+    line: File,
+    /// Sensor model, determining the start signal and the interpretation of the data.
+    model: Model,
+    /// Method used to classify the bits of the last successful reading.
+    bit_decoding: Option<BitDecoding>,
+    /// Timeouts ending an incomplete capture.
+    timeouts: CaptureTimeouts,
+}
+
+impl CdevController {
+    /// Requests the line `offset` of the GPIO chip at `chip` (e.g. `/dev/gpiochip0`) for the
+    /// sensor, the line is held until the controller is dropped.
+    pub fn new<C: AsRef<Path>>(chip: C, offset: u32) -> io::Result<CdevController> {
+        let chip = OpenOptions::new().read(true).write(true).open(chip)?;
+
+        // SAFETY: the struct only consists of integers, for which all zeroes is valid.
+        let mut request: LineRequest = unsafe { mem::zeroed() };
+        request.offsets[0] = offset;
+        request.consumer[..CONSUMER.len()].copy_from_slice(CONSUMER);
+        request.config = line_config(GPIO_V2_LINE_FLAG_OUTPUT, Some(Level::High));
+        request.num_lines = 1;
+        request.event_buffer_size = EVENT_BUFFER_SIZE;
+        ioctl(chip.as_raw_fd(), GPIO_V2_GET_LINE_IOCTL, &mut request)?;
+
+        // SAFETY: the kernel returned a newly opened file descriptor owned by us.
+        let line = unsafe { File::from_raw_fd(request.fd) };
+        Ok(CdevController {
+            line,
+            model: Model::DHT11,
+            bit_decoding: None,
+            timeouts: CaptureTimeouts::default(),
+        })
+    }
+
+    /// Sets the sensor model connected to the line, by default a DHT11.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Returns the sensor model connected to the line.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Returns the method used to classify the bits of the last successful reading.
+    pub fn last_bit_decoding(&self) -> Option<BitDecoding> {
+        self.bit_decoding
+    }
+
+    /// Sets the maximum duration of a capture, by default 200ms, which only matters if the edges
+    /// of the last bit never arrive.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.timeouts.deadline = deadline;
+        self
+    }
+
+    /// Sets the maximum duration waiting for the first edge of the sensor response, by default
+    /// 10ms, after which the read fails with `DHT11Error::NoResponse`.
+    pub fn with_no_response_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.no_response = timeout;
+        self
+    }
+
+    /// Reconfigures the requested line.
+    fn set_config(&mut self, flags: u64, output: Option<Level>) -> io::Result<()> {
+        let mut config = line_config(flags, output);
+        ioctl(
+            self.line.as_raw_fd(),
+            GPIO_V2_LINE_SET_CONFIG_IOCTL,
+            &mut config,
+        )
+    }
+
+    /// Drives the requested line, which has to be configured as output.
+    fn set_value(&mut self, level: Level) -> io::Result<()> {
+        let mut values = LineValues {
+            bits: (level == Level::High) as u64,
+            mask: 1,
+        };
+        ioctl(
+            self.line.as_raw_fd(),
+            GPIO_V2_LINE_SET_VALUES_IOCTL,
+            &mut values,
+        )
+    }
+
+    /// Collects the edge events of the sensor response until the parser is complete, feeding the
+    /// time between two edges into the parser as the length of a pull-up or pull-down in
+    /// microseconds, without allocating. Any edge counts as a response of the sensor.
+    fn collect_edges(&mut self, parser: &mut PulseParser) -> io::Result<()> {
+        // Level after the last edge with its kernel timestamp
+        let mut last: Option<(Level, u64)> = None;
+        let mut events = [LineEvent {
+            timestamp_ns: 0,
+            id: 0,
+            offset: 0,
+            seqno: 0,
+            line_seqno: 0,
+            padding: [0; 6],
+        }; 16];
+
+        let begin = Instant::now();
+
+        loop {
+            let remaining = self
+                .timeouts
+                .limit(last.is_some())
+                .saturating_sub(begin.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            let mut poll_fd = libc::pollfd {
+                fd: self.line.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: `poll_fd` is a single valid pollfd.
+            let ret =
+                unsafe { libc::poll(&mut poll_fd, 1, remaining.as_micros().div_ceil(1000) as i32) };
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if ret == 0 {
+                break;
+            }
+
+            // SAFETY: `LineEvent` is plain old data, so viewing the buffer as bytes is valid.
+            let buffer = unsafe {
+                std::slice::from_raw_parts_mut(
+                    events.as_mut_ptr() as *mut u8,
+                    mem::size_of_val(&events),
+                )
+            };
+            let read = self.line.read(buffer)?;
+            for event in &events[..read / mem::size_of::<LineEvent>()] {
+                let level = if event.id == GPIO_V2_LINE_EVENT_RISING_EDGE {
+                    Level::High
+                } else {
+                    Level::Low
+                };
+                if let Some((last_level, start)) = last {
+                    let length = event.timestamp_ns.saturating_sub(start) / 1000;
+                    parser.feed_pulse(last_level, length as u32);
+                }
+                last = Some((level, event.timestamp_ns));
+                if parser.is_complete() {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Sensor<DHT11Result, DHT11Error<io::Error>> for CdevController {
+    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error<io::Error>> {
+        // Sending power pulse to indicate a start signal for the sensor
+        self.set_config(GPIO_V2_LINE_FLAG_OUTPUT, Some(Level::High))?;
+        thread::sleep(Duration::from_millis(50));
+        self.set_value(Level::Low)?;
+        thread::sleep(self.model.start_signal());
+
+        // Receiving data as edges, the time between two edges is the length of a pull-up or
+        // pull-down in microseconds
+        self.set_config(
+            GPIO_V2_LINE_FLAG_INPUT
+                | GPIO_V2_LINE_FLAG_EDGE_RISING
+                | GPIO_V2_LINE_FLAG_EDGE_FALLING
+                | GPIO_V2_LINE_FLAG_BIAS_PULL_UP,
+            None,
+        )?;
+        let mut parser = PulseParser::new(PulseUnit::Microseconds);
+        self.collect_edges(&mut parser)?;
+
+        let (result, bit_decoding) = decode_reading(&parser, self.model)?;
+        self.bit_decoding = Some(bit_decoding);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    /// Configfs directory of the `gpio-sim` kernel module.
+    const GPIO_SIM: &str = "/sys/kernel/config/gpio-sim";
+
+    /// Simulated GPIO chip with a single line, removed when dropped.
+    struct SimChip {
+        config: PathBuf,
+        chip: PathBuf,
+        line: PathBuf,
+    }
+
+    impl SimChip {
+        fn new() -> io::Result<SimChip> {
+            let config = Path::new(GPIO_SIM).join(format!("dht11_gpio-{}", std::process::id()));
+            fs::create_dir(&config)?;
+            fs::create_dir(config.join("bank0"))?;
+            fs::write(config.join("bank0/num_lines"), "1")?;
+            fs::write(config.join("live"), "1")?;
+
+            let dev_name = fs::read_to_string(config.join("dev_name"))?;
+            let chip_name = fs::read_to_string(config.join("bank0/chip_name"))?;
+            let line = Path::new("/sys/devices/platform")
+                .join(dev_name.trim())
+                .join(chip_name.trim())
+                .join("sim_gpio0");
+            Ok(SimChip {
+                config,
+                chip: Path::new("/dev").join(chip_name.trim()),
+                line,
+            })
+        }
+
+        fn value(&self) -> io::Result<Level> {
+            match fs::read_to_string(self.line.join("value"))?.trim() {
+                "0" => Ok(Level::Low),
+                _ => Ok(Level::High),
+            }
+        }
+
+        fn pull(&self, level: Level) -> io::Result<()> {
+            let pull = match level {
+                Level::Low => "pull-down",
+                Level::High => "pull-up",
+            };
+            fs::write(self.line.join("pull"), pull)
+        }
+    }
+
+    impl Drop for SimChip {
+        fn drop(&mut self) {
+            let _ = fs::write(self.config.join("live"), "0");
+            let _ = fs::remove_dir(self.config.join("bank0"));
+            let _ = fs::remove_dir(&self.config);
+        }
+    }
+
+    /// Responds to the start signal by pulling the simulated line, with the timings scaled up
+    /// to what can be generated from user space (300µs for a `0`, 900µs for a `1`).
+    fn respond(sim: &SimChip, bytes: [u8; 5]) -> io::Result<()> {
+        let begin = Instant::now();
+        while sim.value()? == Level::High {
+            if begin.elapsed() > Duration::from_secs(1) {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            thread::sleep(Duration::from_micros(100));
+        }
+        // Responding shortly after the 20ms start signal
+        thread::sleep(Duration::from_millis(25));
+
+        let mut pulses = vec![(Level::Low, 300), (Level::High, 300)];
+        for i in 0..DATA_BITS {
+            let bit = bytes[i / 8] & (0x80 >> (i % 8)) != 0;
+            pulses.push((Level::Low, 300));
+            pulses.push((Level::High, if bit { 900 } else { 300 }));
+        }
+        pulses.push((Level::Low, 300));
+        for (level, length) in pulses {
+            sim.pull(level)?;
+            thread::sleep(Duration::from_micros(length));
+        }
+        sim.pull(Level::High)
+    }
+
+    #[test]
+    #[ignore = "needs the gpio-sim kernel module, root and configfs"]
+    fn reads_gpio_sim() {
+        assert!(
+            Path::new(GPIO_SIM).is_dir(),
+            "the gpio-sim kernel module is not loaded"
+        );
+        let sim = SimChip::new().unwrap();
+        sim.pull(Level::High).unwrap();
+        let mut controller = CdevController::new(&sim.chip, 0)
+            .unwrap()
+            .with_no_response_timeout(Duration::from_millis(50));
+
+        let result = thread::scope(|scope| {
+            let sensor = scope.spawn(|| respond(&sim, [45, 0, 21, 3, 69]));
+            let result = controller.read_sensor_data();
+            sensor.join().unwrap().unwrap();
+            result
+        })
+        .unwrap();
+        assert_eq!(result.humidity, 45.0);
+        assert_eq!(result.temperature, 21.3);
+        assert_eq!(controller.last_bit_decoding(), Some(BitDecoding::Relative));
+    }
+}
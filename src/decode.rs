@@ -0,0 +1,654 @@
+//! Pure decoding of DHT11 transmissions, usable without a sensor, e.g. to decode captures of a
+//! logic analyzer.
+
+use crate::model::Model;
+use crate::pin::Level;
+use crate::{DHT11Error, DHT11Result};
+use core::time::Duration;
+
+/// Number of data bits in a DHT11 transmission (4 byte data + 1 byte checksum).
+pub const DATA_BITS: usize = 40;
+
+/// Number of bytes in a DHT11 transmission (4 byte data + 1 byte checksum).
+pub const DATA_BYTES: usize = DATA_BITS / 8;
+
+/// Pull-ups shorter than this can not be a valid bit.
+const MIN_PULL_UP: u16 = 10; // microseconds
+/// Pull-ups up to this length are a `0` bit (datasheet: 26-28µs).
+const MAX_ZERO_PULL_UP: u16 = 45; // microseconds
+/// Pull-ups from this length on are a `1` bit (datasheet: 70µs).
+const MIN_ONE_PULL_UP: u16 = 55; // microseconds
+/// Pull-ups longer than this can not be a valid bit.
+const MAX_PULL_UP: u16 = 100; // microseconds
+/// Pull-ups from this length on are a `1` bit when only the absolute thresholds are used, the
+/// midpoint between the `0` and `1` thresholds.
+const ONE_THRESHOLD: u16 = (MAX_ZERO_PULL_UP + MIN_ONE_PULL_UP) / 2; // microseconds
+
+/// Method used to classify the pull-up lengths into bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitDecoding {
+    /// The pull-up durations were classified using the absolute thresholds from the datasheet
+    /// (≈26-28µs for a `0`, ≈70µs for a `1`).
+    Absolute,
+    /// The pull-up lengths were classified relative to the midpoint between the shortest and the
+    /// longest pull-up, used when the lengths are not in microseconds or outside of the datasheet
+    /// thresholds.
+    Relative,
+}
+
+/// Strategy used to classify the pull-up lengths into bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThresholdStrategy {
+    /// Uses the absolute thresholds from the datasheet, falling back to the relative midpoint if
+    /// the lengths are not in microseconds or do not fit the thresholds.
+    #[default]
+    Auto,
+    /// Only uses the absolute thresholds, classifying pull-ups of 50µs and longer as a `1`, even
+    /// if they are outside of the datasheet thresholds. Lengths which are not in microseconds
+    /// are still classified relative to each other.
+    Absolute,
+    /// Only uses the midpoint between the shortest and the longest pull-up, e.g. for sensors
+    /// with timings far off the datasheet.
+    Relative,
+}
+
+/// Unit of the pulse lengths fed into a `PulseParser`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PulseUnit {
+    /// Durations in microseconds, which can be classified using the absolute thresholds.
+    Microseconds,
+    /// Number of samples, which can only be classified relative to each other.
+    Samples,
+}
+
+/// Represents different states in DHT11 sensor communication protocol
+#[derive(Debug, Clone, Copy)]
+enum State {
+    InitPullDown,
+    InitPullUp,
+    DataFirstPullDown,
+    DataPullUp,
+    DataPullDown,
+}
+
+/// Incremental parser measuring the lengths of the pull-up states in the DHT11 sensor
+/// communication data, fed one pulse (a level and how long it was held) at a time without
+/// allocating.
+///
+/// The lengths are stored saturated to `u16`, which is plenty for pull-ups of about 70µs.
+#[derive(Debug, Clone)]
+pub struct PulseParser {
+    unit: PulseUnit,
+    strategy: ThresholdStrategy,
+    state: State,
+    current_length: u32,
+    lengths: [u16; DATA_BITS],
+    count: usize,
+}
+
+impl PulseParser {
+    /// Creates a parser waiting for the initial pull-down of the sensor response.
+    pub fn new(unit: PulseUnit) -> PulseParser {
+        PulseParser {
+            unit,
+            strategy: ThresholdStrategy::Auto,
+            state: State::InitPullDown,
+            current_length: 0,
+            lengths: [0; DATA_BITS],
+            count: 0,
+        }
+    }
+
+    /// Sets the strategy used to classify the pull-up lengths into bits, by default the absolute
+    /// thresholds with a fallback to the relative midpoint.
+    pub fn with_strategy(mut self, strategy: ThresholdStrategy) -> PulseParser {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Feeds the next pulse into the parser, `length` is how long `level` was held.
+    pub fn feed_pulse(&mut self, level: Level, length: u32) {
+        // Transitioning from states to other states to determine the lengths
+        match self.state {
+            State::InitPullDown => {
+                if level == Level::Low {
+                    self.state = State::InitPullUp;
+                }
+            }
+            State::InitPullUp => {
+                if level == Level::High {
+                    self.state = State::DataFirstPullDown;
+                }
+            }
+            State::DataFirstPullDown => {
+                if level == Level::Low {
+                    self.state = State::DataPullUp;
+                }
+            }
+            State::DataPullUp => {
+                if level == Level::High {
+                    self.current_length = length;
+                    self.state = State::DataPullDown;
+                }
+            }
+            State::DataPullDown => {
+                // A bit is complete once the sensor pulls the line down again
+                if level == Level::Low {
+                    if self.count < DATA_BITS {
+                        self.lengths[self.count] = saturate(self.current_length);
+                    }
+                    self.count += 1;
+                    self.state = State::DataPullUp;
+                } else {
+                    self.current_length = self.current_length.saturating_add(length);
+                }
+            }
+        }
+    }
+
+    /// Whether all 40 bits were received. The last bit is complete with its trailing low, after
+    /// which the sensor releases the line, so a live capture ends here and only offline decoding
+    /// can see more than 40 bits.
+    pub fn is_complete(&self) -> bool {
+        self.count >= DATA_BITS
+    }
+
+    /// Number of pull-ups seen so far, this can exceed the 40 bits that are stored.
+    pub fn bit_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the pull-up lengths if exactly 40 bits were received.
+    pub fn pull_up_lengths(&self) -> Option<&[u16; DATA_BITS]> {
+        if self.count == DATA_BITS {
+            Some(&self.lengths)
+        } else {
+            None
+        }
+    }
+
+    /// Unit of the pulse lengths fed into the parser.
+    pub fn unit(&self) -> PulseUnit {
+        self.unit
+    }
+
+    /// Evidence of the pull-ups received so far.
+    pub fn evidence(&self) -> Evidence {
+        Evidence {
+            bit_count: self.count,
+            lengths: self.lengths,
+            unit: self.unit,
+            bytes: None,
+        }
+    }
+
+    /// Decodes the received pull-up lengths into a frame, fails if not exactly 40 bits were
+    /// received.
+    pub fn finish(&self) -> Result<DHT11Frame, DHT11Error> {
+        match self.state {
+            // The line was never pulled low
+            State::InitPullDown => Err(DHT11Error::NoResponse),
+            // The sensor responded, but never started sending the data
+            State::InitPullUp | State::DataFirstPullDown => {
+                Err(DHT11Error::PreambleTimeout(self.evidence()))
+            }
+            State::DataPullUp | State::DataPullDown => match self.count {
+                // Bit count mismatch occurred
+                count if count < DATA_BITS => Err(DHT11Error::TooFewBits(self.evidence())),
+                count if count > DATA_BITS => Err(DHT11Error::TooManyBits(self.evidence())),
+                _ => Ok(decode_lengths(&self.lengths, self.unit, self.strategy)),
+            },
+        }
+    }
+}
+
+/// Saturates a pulse length to the `u16` it is stored as.
+fn saturate(length: u32) -> u16 {
+    length.min(u16::MAX as u32) as u16
+}
+
+/// Evidence captured during a failed read, to make the errors actionable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evidence {
+    /// Number of bits received, which can exceed the 40 stored pull-up lengths.
+    pub bit_count: usize,
+    /// Lengths of the received pull-ups, only the first `bit_count` (at most 40) are valid, see
+    /// `pull_up_lengths()`.
+    pub lengths: [u16; DATA_BITS],
+    /// Unit of the pull-up lengths.
+    pub unit: PulseUnit,
+    /// Raw bytes, if all 40 bits were received.
+    pub bytes: Option<[u8; DATA_BYTES]>,
+}
+
+impl Evidence {
+    /// Lengths of the received pull-ups.
+    pub fn pull_up_lengths(&self) -> &[u16] {
+        &self.lengths[..self.bit_count.min(DATA_BITS)]
+    }
+}
+
+/// Raw 5 byte frame received from the DHT11 sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DHT11Frame {
+    /// 4 data bytes followed by the checksum byte.
+    pub bytes: [u8; DATA_BYTES],
+    /// Method used to classify the bits of the frame.
+    pub bit_decoding: BitDecoding,
+    /// Lengths of the 40 pull-ups the bits were decoded from.
+    pub pull_up_lengths: [u16; DATA_BITS],
+    /// Unit of the pull-up lengths.
+    pub unit: PulseUnit,
+}
+
+impl DHT11Frame {
+    /// Checksum calculated from the 4 data bytes.
+    pub fn calculated_checksum(&self) -> u8 {
+        calculate_checksum(&self.bytes)
+    }
+
+    /// Whether the calculated checksum matches the validation checksum (last byte).
+    pub fn is_checksum_valid(&self) -> bool {
+        self.bytes[4] == self.calculated_checksum()
+    }
+
+    /// Humidity in percentage, interpreting the frame as sent by a DHT11.
+    pub fn humidity(&self) -> f64 {
+        Model::DHT11.humidity(&self.bytes)
+    }
+
+    /// Temperature in degrees Celsius, interpreting the frame as sent by a DHT11.
+    pub fn temperature(&self) -> f64 {
+        Model::DHT11.temperature(&self.bytes)
+    }
+
+    /// Evidence of the frame, included in the errors.
+    pub fn evidence(&self) -> Evidence {
+        Evidence {
+            bit_count: DATA_BITS,
+            lengths: self.pull_up_lengths,
+            unit: self.unit,
+            bytes: Some(self.bytes),
+        }
+    }
+
+    /// Validates the checksum of the frame, fails with `DHT11Error::InvalidChecksum` if it does
+    /// not match.
+    pub fn check(&self) -> Result<(), DHT11Error> {
+        if !self.is_checksum_valid() {
+            // The checksum does not match the validation checksum
+            return Err(DHT11Error::InvalidChecksum {
+                expected: self.calculated_checksum(),
+                got: self.bytes[4],
+                evidence: self.evidence(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Converts the frame into a DHT11 reading, fails with `DHT11Error::InvalidChecksum` if the
+    /// checksum does not match, or `DHT11Error::OutOfRange` if the values can not be measured.
+    pub fn to_result(&self) -> Result<DHT11Result, DHT11Error> {
+        self.to_model_result(Model::DHT11)
+    }
+
+    /// Converts the frame into a reading of the specified sensor model, fails with
+    /// `DHT11Error::InvalidChecksum` if the checksum does not match, or `DHT11Error::OutOfRange`
+    /// if the values are outside of the measuring range of the model.
+    pub fn to_model_result(&self, model: Model) -> Result<DHT11Result, DHT11Error> {
+        self.check()?;
+        let result = DHT11Result {
+            temperature: model.temperature(&self.bytes),
+            humidity: model.humidity(&self.bytes),
+        };
+        if !model.is_in_range(&result) {
+            return Err(DHT11Error::OutOfRange(self.evidence()));
+        }
+        Ok(result)
+    }
+}
+
+/// Parses the lengths of the pull-up states from a sequence of sampled levels, where each
+/// sample counts as one unit of length.
+pub fn parse_data_pull_up_lengths(data: &[Level]) -> PulseParser {
+    let mut parser = PulseParser::new(PulseUnit::Samples);
+    let mut samples = data.iter().copied();
+
+    if let Some(mut last) = samples.next() {
+        let mut length: u32 = 1;
+        for current in samples {
+            if current != last {
+                parser.feed_pulse(last, length);
+                last = current;
+                length = 0;
+            }
+            length += 1;
+        }
+        parser.feed_pulse(last, length);
+    }
+    parser
+}
+
+/// Parses the lengths of the pull-up states from a list of pulses, each being a level and how
+/// long it was held.
+pub fn parse_pulses(pulses: &[(Level, Duration)]) -> PulseParser {
+    let mut parser = PulseParser::new(PulseUnit::Microseconds);
+    for &(level, duration) in pulses {
+        parser.feed_pulse(level, duration.as_micros() as u32);
+    }
+    parser
+}
+
+/// Decodes a frame from a sequence of sampled levels, e.g. captured by a logic analyzer.
+pub fn decode_levels(data: &[Level]) -> Result<DHT11Frame, DHT11Error> {
+    parse_data_pull_up_lengths(data).finish()
+}
+
+/// Decodes a frame from a list of pulses, each being a level and how long it was held.
+pub fn decode_pulses(pulses: &[(Level, Duration)]) -> Result<DHT11Frame, DHT11Error> {
+    parse_pulses(pulses).finish()
+}
+
+/// Decodes a frame from the lengths of the 40 data pull-ups.
+pub fn decode_pull_up_lengths(lengths: &[u16], unit: PulseUnit) -> Result<DHT11Frame, DHT11Error> {
+    let mut parser = PulseParser::new(unit);
+    parser.state = State::DataPullUp;
+    for &length in lengths {
+        parser.feed_pulse(Level::High, length as u32);
+        parser.feed_pulse(Level::Low, 0);
+    }
+    parser.finish()
+}
+
+/// Decodes a frame from the lengths of exactly 40 data pull-ups.
+fn decode_lengths(
+    lengths: &[u16; DATA_BITS],
+    unit: PulseUnit,
+    strategy: ThresholdStrategy,
+) -> DHT11Frame {
+    let (bits, bit_decoding) = calculate_bits_with(lengths, unit, strategy);
+    DHT11Frame {
+        bytes: bits_to_bytes(&bits),
+        bit_decoding,
+        pull_up_lengths: *lengths,
+        unit,
+    }
+}
+
+/// Calculates bits from the pull-up durations using the absolute thresholds from the datasheet,
+/// returns `None` if any of the durations is ambiguous or out of range.
+fn calculate_bits_absolute(pull_up_lengths: &[u16; DATA_BITS]) -> Option<[bool; DATA_BITS]> {
+    let mut bits = [false; DATA_BITS];
+
+    for (bit, &length) in bits.iter_mut().zip(pull_up_lengths) {
+        *bit = match length {
+            MIN_PULL_UP..=MAX_ZERO_PULL_UP => false,
+            MIN_ONE_PULL_UP..=MAX_PULL_UP => true,
+            _ => return None,
+        };
+    }
+    Some(bits)
+}
+
+/// Calculates bits from the pull-up durations using the midpoint between the absolute thresholds
+/// from the datasheet, regardless of whether they are in range.
+fn calculate_bits_threshold(pull_up_lengths: &[u16; DATA_BITS]) -> [bool; DATA_BITS] {
+    let mut bits = [false; DATA_BITS];
+
+    for (bit, &length) in bits.iter_mut().zip(pull_up_lengths) {
+        *bit = length >= ONE_THRESHOLD;
+    }
+    bits
+}
+
+/// Calculates bits from the pull-up lengths in the DHT11 sensor communication data, relative to
+/// the midpoint between the shortest and the longest pull-up.
+fn calculate_bits_relative(pull_up_lengths: &[u16; DATA_BITS]) -> [bool; DATA_BITS] {
+    let mut shortest_pull_up: u16 = u16::MAX;
+    let mut longest_pull_up: u16 = 0;
+
+    for &length in pull_up_lengths {
+        if length < shortest_pull_up {
+            shortest_pull_up = length
+        }
+        if length > longest_pull_up {
+            longest_pull_up = length
+        }
+    }
+
+    let halfway = shortest_pull_up + (longest_pull_up - shortest_pull_up) / 2;
+    let mut bits = [false; DATA_BITS];
+
+    for (bit, &length) in bits.iter_mut().zip(pull_up_lengths) {
+        *bit = length > halfway;
+    }
+    bits
+}
+
+/// Calculates bits from the pull-up lengths in the DHT11 sensor communication data.
+///
+/// Durations in microseconds are classified using the absolute thresholds from the datasheet,
+/// falling back to the relative midpoint if they do not fit.
+pub fn calculate_bits(
+    pull_up_lengths: &[u16; DATA_BITS],
+    unit: PulseUnit,
+) -> ([bool; DATA_BITS], BitDecoding) {
+    if unit == PulseUnit::Microseconds {
+        if let Some(bits) = calculate_bits_absolute(pull_up_lengths) {
+            return (bits, BitDecoding::Absolute);
+        }
+    }
+    (
+        calculate_bits_relative(pull_up_lengths),
+        BitDecoding::Relative,
+    )
+}
+
+/// Calculates bits from the pull-up lengths in the DHT11 sensor communication data, using the
+/// specified threshold strategy.
+pub fn calculate_bits_with(
+    pull_up_lengths: &[u16; DATA_BITS],
+    unit: PulseUnit,
+    strategy: ThresholdStrategy,
+) -> ([bool; DATA_BITS], BitDecoding) {
+    match strategy {
+        ThresholdStrategy::Auto => calculate_bits(pull_up_lengths, unit),
+        ThresholdStrategy::Absolute if unit == PulseUnit::Microseconds => (
+            calculate_bits_threshold(pull_up_lengths),
+            BitDecoding::Absolute,
+        ),
+        ThresholdStrategy::Absolute | ThresholdStrategy::Relative => (
+            calculate_bits_relative(pull_up_lengths),
+            BitDecoding::Relative,
+        ),
+    }
+}
+
+/// Converts bits into bytes in the DHT11 sensor communication data.
+pub fn bits_to_bytes(bits: &[bool; DATA_BITS]) -> [u8; DATA_BYTES] {
+    let mut bytes = [0u8; DATA_BYTES];
+
+    for (i, bit) in bits.iter().enumerate() {
+        bytes[i / 8] <<= 1;
+        if *bit {
+            bytes[i / 8] |= 1;
+        }
+    }
+    bytes
+}
+
+/// Calculates the checksum from the bytes in the DHT11 sensor communication data.
+pub fn calculate_checksum(bytes: &[u8; DATA_BYTES]) -> u8 {
+    bytes[0]
+        .wrapping_add(bytes[1])
+        .wrapping_add(bytes[2])
+        .wrapping_add(bytes[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 45% humidity and 21.3°C with a valid checksum.
+    const BYTES: [u8; DATA_BYTES] = [45, 0, 21, 3, 69];
+
+    /// Pull-up lengths of the bits of the bytes, with the specified lengths for a `0` and a `1`.
+    fn lengths(bytes: &[u8; DATA_BYTES], zero: u16, one: u16) -> [u16; DATA_BITS] {
+        let mut lengths = [0; DATA_BITS];
+        for (i, length) in lengths.iter_mut().enumerate() {
+            let bit = bytes[i / 8] & (0x80 >> (i % 8)) != 0;
+            *length = if bit { one } else { zero };
+        }
+        lengths
+    }
+
+    #[test]
+    fn absolute_boundaries() {
+        // The first bit of `BYTES` is a `0`, the third a `1`
+        for (index, length, decoding) in [
+            (0, 10, BitDecoding::Absolute),
+            (0, 9, BitDecoding::Relative),
+            (0, 45, BitDecoding::Absolute),
+            (0, 46, BitDecoding::Relative),
+            (2, 55, BitDecoding::Absolute),
+            (2, 54, BitDecoding::Relative),
+            (2, 100, BitDecoding::Absolute),
+            (2, 101, BitDecoding::Relative),
+        ] {
+            let mut lengths = lengths(&BYTES, 27, 70);
+            lengths[index] = length;
+            let (bits, bit_decoding) = calculate_bits(&lengths, PulseUnit::Microseconds);
+            assert_eq!(bit_decoding, decoding, "pull-up of {}µs", length);
+            if decoding == BitDecoding::Absolute {
+                assert_eq!(bits_to_bytes(&bits), BYTES, "pull-up of {}µs", length);
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_relative() {
+        // A slow sensor, outside of the datasheet thresholds
+        let (bits, bit_decoding) =
+            calculate_bits(&lengths(&BYTES, 120, 300), PulseUnit::Microseconds);
+        assert_eq!(bit_decoding, BitDecoding::Relative);
+        assert_eq!(bits_to_bytes(&bits), BYTES);
+
+        // Samples are never classified with the absolute thresholds
+        let (bits, bit_decoding) = calculate_bits(&lengths(&BYTES, 27, 70), PulseUnit::Samples);
+        assert_eq!(bit_decoding, BitDecoding::Relative);
+        assert_eq!(bits_to_bytes(&bits), BYTES);
+    }
+
+    #[test]
+    fn explicit_absolute() {
+        let mut lengths = lengths(&BYTES, 30, 120);
+        // Classified around the midpoint of 50µs, even outside of the datasheet thresholds
+        lengths[0] = 49;
+        lengths[2] = 50;
+        let (bits, bit_decoding) = calculate_bits_with(
+            &lengths,
+            PulseUnit::Microseconds,
+            ThresholdStrategy::Absolute,
+        );
+        assert_eq!(bit_decoding, BitDecoding::Absolute);
+        assert_eq!(bits_to_bytes(&bits), BYTES);
+
+        let (_, bit_decoding) =
+            calculate_bits_with(&lengths, PulseUnit::Samples, ThresholdStrategy::Absolute);
+        assert_eq!(bit_decoding, BitDecoding::Relative);
+    }
+
+    #[test]
+    fn explicit_relative() {
+        let lengths = lengths(&BYTES, 27, 70);
+        let (bits, bit_decoding) = calculate_bits_with(
+            &lengths,
+            PulseUnit::Microseconds,
+            ThresholdStrategy::Relative,
+        );
+        assert_eq!(bit_decoding, BitDecoding::Relative);
+        assert_eq!(bits_to_bytes(&bits), BYTES);
+
+        // The parser uses the strategy as well
+        let mut parser =
+            PulseParser::new(PulseUnit::Microseconds).with_strategy(ThresholdStrategy::Relative);
+        parser.state = State::DataPullUp;
+        for length in lengths {
+            parser.feed_pulse(Level::High, length as u32);
+            parser.feed_pulse(Level::Low, 50);
+        }
+        assert_eq!(parser.finish().unwrap().bit_decoding, BitDecoding::Relative);
+    }
+
+    #[test]
+    fn decodes_pull_up_lengths() {
+        let lengths = lengths(&BYTES, 27, 70);
+        let frame = decode_pull_up_lengths(&lengths, PulseUnit::Microseconds).unwrap();
+        assert_eq!(frame.bytes, BYTES);
+        assert_eq!(frame.bit_decoding, BitDecoding::Absolute);
+        assert_eq!(frame.pull_up_lengths, lengths);
+        assert!(frame.is_checksum_valid());
+        assert_eq!(frame.humidity(), 45.0);
+        assert_eq!(frame.temperature(), 21.3);
+
+        match decode_pull_up_lengths(&lengths[..39], PulseUnit::Microseconds) {
+            Err(DHT11Error::TooFewBits(evidence)) => assert_eq!(evidence.bit_count, 39),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    /// Feeds the whole signal of the pull-ups into the parser, from the release of the line to
+    /// the trailing low of the last bit.
+    fn feed(parser: &mut PulseParser, lengths: &[u16]) {
+        parser.feed_pulse(Level::High, 30);
+        parser.feed_pulse(Level::Low, 80);
+        parser.feed_pulse(Level::High, 80);
+        for &length in lengths {
+            parser.feed_pulse(Level::Low, 50);
+            parser.feed_pulse(Level::High, length as u32);
+        }
+        parser.feed_pulse(Level::Low, 50);
+    }
+
+    #[test]
+    fn too_many_bits() {
+        let mut pull_ups = [27; DATA_BITS + 2];
+        pull_ups[..DATA_BITS].copy_from_slice(&lengths(&BYTES, 27, 70));
+        let mut parser = PulseParser::new(PulseUnit::Microseconds);
+        feed(&mut parser, &pull_ups);
+        assert!(parser.is_complete());
+        assert_eq!(parser.pull_up_lengths(), None);
+
+        match parser.finish() {
+            Err(DHT11Error::TooManyBits(evidence)) => {
+                assert_eq!(evidence.bit_count, DATA_BITS + 2);
+                assert_eq!(evidence.unit, PulseUnit::Microseconds);
+                assert_eq!(evidence.bytes, None);
+                // Only the first 40 lengths are stored
+                assert_eq!(evidence.pull_up_lengths(), &lengths(&BYTES, 27, 70));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        // 150% humidity with a valid checksum
+        let bytes = [150, 0, 21, 3, 174];
+        let pull_ups = lengths(&bytes, 27, 70);
+        let mut parser = PulseParser::new(PulseUnit::Microseconds);
+        feed(&mut parser, &pull_ups);
+        let frame = parser.finish().unwrap();
+        assert!(frame.is_checksum_valid());
+
+        match frame.to_result() {
+            Err(DHT11Error::OutOfRange(evidence)) => {
+                assert_eq!(evidence.bit_count, DATA_BITS);
+                assert_eq!(evidence.unit, PulseUnit::Microseconds);
+                assert_eq!(evidence.bytes, Some(bytes));
+                assert_eq!(evidence.pull_up_lengths(), &pull_ups);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Generation of synthetic DHT11 signals, the reverse of the `decode` module, to test the
+//! decoding without a sensor.
+
+use crate::decode::{calculate_checksum, DATA_BYTES};
+use crate::pin::Level;
+use std::time::Duration;
+
+/// Durations of the pulses sent by the DHT11 sensor, the defaults are the typical values from the
+/// datasheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalTiming {
+    /// Time the line is pulled up by the resistor after the start signal, before the sensor responds.
+    pub release: Duration,
+    /// Response pull-down of the sensor.
+    pub response_low: Duration,
+    /// Response pull-up of the sensor.
+    pub response_high: Duration,
+    /// Pull-down preceding each bit, and following the last bit.
+    pub bit_low: Duration,
+    /// Pull-up of a `0` bit.
+    pub zero_high: Duration,
+    /// Pull-up of a `1` bit.
+    pub one_high: Duration,
+    /// Time the line stays pulled up after the transmission.
+    pub idle: Duration,
+}
+
+impl Default for SignalTiming {
+    fn default() -> SignalTiming {
+        SignalTiming {
+            release: Duration::from_micros(30),
+            response_low: Duration::from_micros(80),
+            response_high: Duration::from_micros(80),
+            bit_low: Duration::from_micros(50),
+            zero_high: Duration::from_micros(27),
+            one_high: Duration::from_micros(70),
+            idle: Duration::from_millis(1),
+        }
+    }
+}
+
+/// Generator of the signal a DHT11 sensor emits for a frame.
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    /// Nominal durations of the pulses.
+    pub timing: SignalTiming,
+    /// Maximum random offset added to or subtracted from each pulse.
+    pub jitter: Duration,
+    /// Maximum random deviation of each pulse width, relative to its nominal duration (e.g. `0.1`
+    /// for ±10%).
+    pub tolerance: f64,
+    /// State of the pseudo-random generator, the same seed always generates the same signal.
+    seed: u64,
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
+}
+
+/// Appends the checksum to 4 data bytes (humidity integer/decimal, temperature integer/decimal).
+pub fn with_checksum(data: [u8; 4]) -> [u8; DATA_BYTES] {
+    let mut bytes = [data[0], data[1], data[2], data[3], 0];
+    bytes[4] = calculate_checksum(&bytes);
+    bytes
+}
+
+impl Encoder {
+    /// Creates an encoder generating the exact datasheet timing.
+    pub fn new() -> Encoder {
+        Encoder {
+            timing: SignalTiming::default(),
+            jitter: Duration::ZERO,
+            tolerance: 0.0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Sets the seed of the pseudo-random generator used for the jitter and tolerance.
+    pub fn with_seed(mut self, seed: u64) -> Encoder {
+        // xorshift gets stuck at zero
+        self.seed = seed.max(1);
+        self
+    }
+
+    /// Returns a pseudo-random number in `-1.0..=1.0` (xorshift64*).
+    fn random(&mut self) -> f64 {
+        self.seed ^= self.seed >> 12;
+        self.seed ^= self.seed << 25;
+        self.seed ^= self.seed >> 27;
+        let value = self.seed.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        value as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    /// Applies the tolerance and jitter to a nominal duration.
+    fn vary(&mut self, nominal: Duration) -> Duration {
+        let mut micros = nominal.as_secs_f64() * 1e6;
+        micros *= 1.0 + self.random() * self.tolerance;
+        micros += self.random() * self.jitter.as_secs_f64() * 1e6;
+        Duration::from_secs_f64(micros.max(1.0) / 1e6)
+    }
+
+    /// Generates the pulses (level and how long it is held) a DHT11 sensor emits for the bytes,
+    /// from the release of the line after the start signal to the line being idle again.
+    pub fn pulses(&mut self, bytes: &[u8; DATA_BYTES]) -> Vec<(Level, Duration)> {
+        let timing = self.timing;
+        let mut pulses: Vec<(Level, Duration)> = vec![
+            (Level::High, self.vary(timing.release)),
+            (Level::Low, self.vary(timing.response_low)),
+            (Level::High, self.vary(timing.response_high)),
+        ];
+
+        for byte in bytes {
+            for i in (0..8).rev() {
+                let high = if byte & (1 << i) != 0 {
+                    timing.one_high
+                } else {
+                    timing.zero_high
+                };
+                pulses.push((Level::Low, self.vary(timing.bit_low)));
+                pulses.push((Level::High, self.vary(high)));
+            }
+        }
+
+        pulses.push((Level::Low, self.vary(timing.bit_low)));
+        pulses.push((Level::High, timing.idle));
+        pulses
+    }
+
+    /// Generates the levels a DHT11 sensor emits for the bytes, sampled every `sample_period`.
+    pub fn levels(&mut self, bytes: &[u8; DATA_BYTES], sample_period: Duration) -> Vec<Level> {
+        let mut levels: Vec<Level> = vec![];
+        let mut elapsed = Duration::ZERO;
+        let mut sampled: u128 = 0;
+
+        // Sampling the accumulated time keeps the rounding errors from adding up
+        for (level, duration) in self.pulses(bytes) {
+            elapsed += duration;
+            let samples = elapsed.as_nanos() / sample_period.as_nanos().max(1);
+            for _ in sampled..samples {
+                levels.push(level);
+            }
+            sampled = sampled.max(samples);
+        }
+        levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{decode_levels, decode_pulses, BitDecoding};
+
+    /// Frames covering all zero and all one bits, and a typical reading.
+    const FRAMES: [[u8; 4]; 4] = [
+        [0, 0, 0, 0],
+        [0xFF, 0xFF, 0xFF, 0xFF],
+        [45, 0, 21, 3],
+        [0x55, 0xAA, 0x0F, 0xF0],
+    ];
+
+    /// Random frames with valid checksums, the same for every run (xorshift64).
+    fn random_frames(count: usize) -> Vec<[u8; DATA_BYTES]> {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        (0..count)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let data = state.to_le_bytes();
+                with_checksum([data[0], data[1], data[2], data[3]])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn appends_checksum() {
+        assert_eq!(with_checksum([45, 0, 21, 3]), [45, 0, 21, 3, 69]);
+        assert_eq!(
+            with_checksum([0xFF, 0xFF, 0xFF, 0xFF]),
+            [0xFF, 0xFF, 0xFF, 0xFF, 0xFC]
+        );
+    }
+
+    #[test]
+    fn round_trips_pulses() {
+        for data in FRAMES {
+            let bytes = with_checksum(data);
+            let frame = decode_pulses(&Encoder::new().pulses(&bytes)).unwrap();
+            assert_eq!(frame.bytes, bytes);
+            assert_eq!(frame.bit_decoding, BitDecoding::Absolute);
+        }
+    }
+
+    #[test]
+    fn round_trips_levels() {
+        for data in FRAMES {
+            let bytes = with_checksum(data);
+            let levels = Encoder::new().levels(&bytes, Duration::from_micros(1));
+            let frame = decode_levels(&levels).unwrap();
+            assert_eq!(frame.bytes, bytes);
+            assert_eq!(frame.bit_decoding, BitDecoding::Relative);
+        }
+    }
+
+    #[test]
+    fn round_trips_pulses_with_jitter() {
+        // ±10% and ±5µs keep the pull-ups inside the absolute thresholds (≤35µs and ≥58µs)
+        for (seed, bytes) in random_frames(200).into_iter().enumerate() {
+            let mut encoder = Encoder::new().with_seed(seed as u64);
+            encoder.jitter = Duration::from_micros(5);
+            encoder.tolerance = 0.1;
+            let frame = decode_pulses(&encoder.pulses(&bytes)).unwrap();
+            assert_eq!(frame.bytes, bytes, "seed {}", seed);
+            assert_eq!(frame.bit_decoding, BitDecoding::Absolute, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn round_trips_levels_with_jitter() {
+        for (seed, bytes) in random_frames(200).into_iter().enumerate() {
+            let mut encoder = Encoder::new().with_seed(seed as u64);
+            encoder.jitter = Duration::from_micros(5);
+            encoder.tolerance = 0.1;
+            let levels = encoder.levels(&bytes, Duration::from_micros(5));
+            let frame = decode_levels(&levels).unwrap();
+            assert_eq!(frame.bytes, bytes, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_signal() {
+        let bytes = with_checksum([45, 0, 21, 3]);
+        let encoder = |seed| {
+            let mut encoder = Encoder::new().with_seed(seed);
+            encoder.jitter = Duration::from_micros(10);
+            encoder
+        };
+        assert_eq!(encoder(42).pulses(&bytes), encoder(42).pulses(&bytes));
+        assert_ne!(encoder(42).pulses(&bytes), encoder(43).pulses(&bytes));
+    }
+}
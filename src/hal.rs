@@ -0,0 +1,192 @@
+use crate::pin::{Bias, DHT11Pin, Level, PinMode};
+use embedded_hal::digital::{InputPin, OutputPin};
+
+/// Adapter implementing `DHT11Pin` for an `embedded-hal` 1.0 open-drain IO pin.
+///
+/// The wrapped pin has to implement both `InputPin` and `OutputPin` and be configured as
+/// open-drain, switching to input mode releases the line by driving it high so the sensor can
+/// pull it low. The built-in bias can not be changed through `embedded-hal`, so an external
+/// pull-up resistor is required.
+pub struct HalPin<P> {
+    pin: P,
+}
+
+impl<P> HalPin<P> {
+    /// Wraps an `embedded-hal` open-drain pin.
+    pub fn new(pin: P) -> HalPin<P> {
+        HalPin { pin }
+    }
+
+    /// Consumes the adapter, returning the wrapped pin.
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: InputPin + OutputPin> DHT11Pin for HalPin<P> {
+    type Error = P::Error;
+
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error> {
+        match mode {
+            PinMode::Input => self.pin.set_high(),
+            PinMode::Output => Ok(()),
+        }
+    }
+
+    fn set_bias(&mut self, _bias: Bias) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_high()
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_low()
+    }
+
+    fn read(&mut self) -> Result<Level, Self::Error> {
+        Ok(if self.pin.is_high()? {
+            Level::High
+        } else {
+            Level::Low
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::encode::Encoder;
+    use crate::{
+        DHT11Controller, DHT11ControllerBuilder, ErrorKind, RetryPolicy, RetryingReader, Sensor,
+        ThresholdStrategy,
+    };
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::digital::{self, ErrorType};
+    use std::time::{Duration, Instant};
+
+    /// Open-drain pin with a sensor responding in real time once the line is released after
+    /// being driven low. Without timestamps of the pin the controller measures the pulses with
+    /// the system clock, so the sensor is slowed down to keep the scheduling jitter of the test
+    /// from changing the bits.
+    struct OpenDrainPin {
+        pulses: Vec<(Level, Duration)>,
+        /// Levels written to the output, in order.
+        writes: Vec<bool>,
+        driven_low: bool,
+        released: Option<Instant>,
+        /// Whether reading the pin fails.
+        broken: bool,
+    }
+
+    #[derive(Debug)]
+    struct PinError;
+
+    impl digital::Error for PinError {
+        fn kind(&self) -> digital::ErrorKind {
+            digital::ErrorKind::Other
+        }
+    }
+
+    impl ErrorType for OpenDrainPin {
+        type Error = PinError;
+    }
+
+    impl OutputPin for OpenDrainPin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.writes.push(false);
+            self.driven_low = true;
+            self.released = None;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.writes.push(true);
+            if self.driven_low {
+                self.released = Some(Instant::now());
+            }
+            self.driven_low = false;
+            Ok(())
+        }
+    }
+
+    impl InputPin for OpenDrainPin {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            if self.broken {
+                return Err(PinError);
+            }
+            if self.driven_low {
+                return Ok(false);
+            }
+            let elapsed = self
+                .released
+                .map_or(Duration::MAX, |released| released.elapsed());
+            let mut end = Duration::ZERO;
+            for &(level, duration) in &self.pulses {
+                end += duration;
+                if elapsed < end {
+                    return Ok(level == Level::High);
+                }
+            }
+            Ok(true)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    /// Factor the pulses of the sensor are slowed down by.
+    const SLOWDOWN: u32 = 100;
+
+    /// The sensor responds to any start signal, so there is no need to wait for it.
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    fn pin(broken: bool) -> HalPin<OpenDrainPin> {
+        HalPin::new(OpenDrainPin {
+            pulses: Encoder::new()
+                .pulses(&[45, 0, 21, 3, 69])
+                .into_iter()
+                .map(|(level, duration)| (level, duration * SLOWDOWN))
+                .collect(),
+            writes: Vec::new(),
+            driven_low: false,
+            released: None,
+            broken,
+        })
+    }
+
+    #[test]
+    fn reads_open_drain_pin() {
+        let controller = DHT11ControllerBuilder::new()
+            .with_threshold_strategy(ThresholdStrategy::Relative)
+            .with_deadline(Duration::from_secs(2))
+            .build(pin(false), NoDelay)
+            .unwrap();
+        // A preempted test thread can still stretch a pulse beyond the midpoint
+        let mut reader = RetryingReader::new(controller, RetryPolicy::new());
+        let reading = reader.read().unwrap();
+        assert_eq!(reading.result.humidity, 45.0);
+        assert_eq!(reading.result.temperature, 21.3);
+
+        // Every read drives the line high, pulls it low for the start signal and releases it
+        let pin = reader.into_controller().into_pin().into_inner();
+        assert_eq!(pin.writes.len(), 3 * reading.attempts as usize);
+        for writes in pin.writes.chunks(3) {
+            assert_eq!(writes, [true, false, true]);
+        }
+        assert!(!pin.driven_low);
+    }
+
+    #[test]
+    fn reports_pin_errors() {
+        let mut controller = DHT11Controller::with_delay(pin(true), NoDelay);
+        let err = controller.read_sensor_data().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Backend);
+    }
+}
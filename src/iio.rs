@@ -0,0 +1,141 @@
+use crate::{DHT11Error, DHT11Result, Sensor};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default sysfs directory containing the IIO devices.
+const SYSFS_ROOT: &str = "/sys/bus/iio/devices";
+
+/// Value of `ETIMEDOUT` on Linux, returned by the driver when the sensor did not send all bits.
+const ETIMEDOUT: i32 = 110;
+/// Value of `EIO` on Linux, returned by the driver when the checksum is invalid.
+const EIO: i32 = 5;
+
+/// Struct representing a DHT11 sensor read through the Linux `dht11` IIO driver.
+///
+/// The driver (enabled with the `dht11` device-tree overlay on the Raspberry Pi) does the
+/// timing-critical communication in the kernel and exposes the readings in sysfs, so no
+/// userspace bit-banging is needed.
+pub struct IioController {
+    /// sysfs directory of the IIO device, e.g. `/sys/bus/iio/devices/iio:device0`.
+    device: PathBuf,
+}
+
+impl IioController {
+    /// Creates a new IioController instance for the IIO device with the specified number.
+    pub fn new(device: u32) -> IioController {
+        IioController::with_root(SYSFS_ROOT, device)
+    }
+
+    /// Creates a new IioController instance for the IIO device with the specified number inside
+    /// a custom sysfs root directory.
+    pub fn with_root<R: AsRef<Path>>(root: R, device: u32) -> IioController {
+        IioController {
+            device: root.as_ref().join(format!("iio:device{}", device)),
+        }
+    }
+
+    /// Finds the first IIO device named `dht11`.
+    pub fn find() -> io::Result<IioController> {
+        IioController::find_in(SYSFS_ROOT)
+    }
+
+    /// Finds the first IIO device named `dht11` inside a custom sysfs root directory.
+    pub fn find_in<R: AsRef<Path>>(root: R) -> io::Result<IioController> {
+        let mut devices: Vec<PathBuf> = fs::read_dir(root)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        devices.sort();
+
+        for device in devices {
+            if let Ok(name) = fs::read_to_string(device.join("name")) {
+                if name.trim().starts_with("dht11") {
+                    return Ok(IioController { device });
+                }
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no dht11 IIO device found",
+        ))
+    }
+
+    /// Reads a channel of the device, converting the value from milli-units.
+    fn read_channel(&self, channel: &str) -> Result<f64, DHT11Error<io::Error>> {
+        let value = fs::read_to_string(self.device.join(channel)).map_err(channel_error)?;
+        let value: i64 = value
+            .trim()
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(value as f64 / 1000.0)
+    }
+}
+
+/// Maps an error reading a channel, the driver reports the failed transactions as OS errors.
+fn channel_error(err: io::Error) -> DHT11Error<io::Error> {
+    match err.raw_os_error() {
+        Some(ETIMEDOUT) => DHT11Error::NoResponse,
+        Some(EIO) => DHT11Error::InvalidData,
+        _ => DHT11Error::Backend(err),
+    }
+}
+
+impl Sensor<DHT11Result, DHT11Error<io::Error>> for IioController {
+    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error<io::Error>> {
+        // Reading the temperature triggers a new transaction, the humidity is then returned from
+        // the same transaction by the driver
+        let temperature = self.read_channel("in_temp_input")?;
+        let humidity = self.read_channel("in_humidityrelative_input")?;
+
+        Ok(DHT11Result {
+            temperature,
+            humidity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::ErrorKind;
+
+    /// Temporary sysfs root with a `dht11` device next to another IIO device.
+    fn root(name: &str) -> TempDir {
+        let root = TempDir::new(name);
+        root.write("iio:device0/name", "mcp3008\n");
+        root.write("iio:device1/name", "dht11@4\n");
+        root.write("iio:device1/in_temp_input", "21300\n");
+        root.write("iio:device1/in_humidityrelative_input", "45000\n");
+        root
+    }
+
+    #[test]
+    fn reads_device() {
+        let root = root("iio");
+        let mut controller = IioController::find_in(root.path()).unwrap();
+        assert_eq!(controller.device, root.path().join("iio:device1"));
+
+        let result = controller.read_sensor_data().unwrap();
+        assert_eq!(result.temperature, 21.3);
+        assert_eq!(result.humidity, 45.0);
+    }
+
+    #[test]
+    fn reports_missing_channel() {
+        let root = root("iio-missing");
+        fs::remove_file(root.path().join("iio:device1/in_humidityrelative_input")).unwrap();
+        let err = IioController::with_root(root.path(), 1)
+            .read_sensor_data()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Backend);
+    }
+
+    #[test]
+    fn maps_driver_errors() {
+        let kind = |errno| channel_error(io::Error::from_raw_os_error(errno)).kind();
+        assert_eq!(kind(ETIMEDOUT), ErrorKind::NoResponse);
+        assert_eq!(kind(EIO), ErrorKind::InvalidData);
+        assert_eq!(kind(13), ErrorKind::Backend);
+    }
+}
@@ -1,8 +1,67 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::convert::Infallible;
 use core::fmt;
-use rppal::gpio::{Bias, Gpio, IoPin, Level, Mode};
+use core::time::Duration;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+#[cfg(feature = "rppal")]
+use rppal::gpio::{Gpio, IoPin, Mode};
+#[cfg(feature = "std")]
 use std::error::Error;
-use std::thread;
-use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+mod builder;
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "cdev")]
+mod cdev;
+pub mod decode;
+#[cfg(feature = "std")]
+mod delay;
+#[cfg(feature = "std")]
+pub mod encode;
+mod hal;
+#[cfg(feature = "std")]
+mod iio;
+mod model;
+#[cfg(feature = "std")]
+mod pigpio;
+mod pin;
+mod retry;
+#[cfg(feature = "std")]
+pub mod sigrok;
+#[cfg(feature = "std")]
+pub mod sim;
+#[cfg(feature = "std")]
+mod sysfs;
+#[cfg(all(test, feature = "std"))]
+mod test_util;
+#[cfg(feature = "std")]
+mod vcd;
+
+#[cfg(feature = "rppal")]
+pub use builder::BuildError;
+pub use builder::{ConfigError, DHT11ControllerBuilder};
+#[cfg(feature = "std")]
+use capture::Capture;
+#[cfg(feature = "cdev")]
+pub use cdev::CdevController;
+pub use decode::{BitDecoding, DHT11Frame, Evidence, ThresholdStrategy};
+use decode::{PulseParser, PulseUnit};
+#[cfg(feature = "std")]
+pub use delay::StdDelay;
+pub use hal::HalPin;
+#[cfg(feature = "std")]
+pub use iio::IioController;
+pub use model::{detect_model, Detection, Model};
+#[cfg(feature = "std")]
+pub use pigpio::PigpioController;
+pub use pin::{Bias, DHT11Pin, Level, PinMode};
+pub use retry::{RetriedReading, RetryPolicy, RetryingReader};
+#[cfg(feature = "std")]
+pub use sysfs::SysfsPin;
 
 /// Trait representing a generic sensor with methods for reading sensor data.
 pub trait Sensor<T, E> {
@@ -11,6 +70,7 @@ pub trait Sensor<T, E> {
 }
 
 /// Struct representing the result of a DHT11 sensor reading, containing temperature and humidity.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DHT11Result {
     /// Temperature in degrees Celsius.
     pub temperature: f64,
@@ -19,211 +79,849 @@ pub struct DHT11Result {
 }
 
 /// Struct representing a DHT11 sensor controller with a GPIO pin.
-pub struct DHT11Controller {
+///
+/// The controller is generic over the pin backend and the delay used for the start signal, by
+/// default the rppal `IoPin` and `std::thread::sleep` are used.
+pub struct DHT11Controller<
+    #[cfg(feature = "rppal")] P = IoPin,
+    #[cfg(not(feature = "rppal"))] P,
+    #[cfg(feature = "std")] D = StdDelay,
+    #[cfg(not(feature = "std"))] D,
+> {
     /// GPIO pin connected to the DHT11 sensor.
-    dht_pin: IoPin,
+    dht_pin: P,
+    /// Delay provider used to time the start signal.
+    delay: D,
+    /// Sensor model, determining the start signal and the interpretation of the data.
+    model: Model,
+    /// Method used to classify the bits of the last successful reading.
+    bit_decoding: Option<BitDecoding>,
+    /// Strategy used to classify the pull-up lengths into bits.
+    strategy: ThresholdStrategy,
+    /// Duration the line is driven high before the start signal.
+    start_high: Duration,
+    /// Duration of the start signal, by default the one of the model.
+    start_signal: Option<Duration>,
+    /// Built-in resistor configured while receiving the response.
+    bias: Bias,
+    /// Whether the pulses of each read are retained.
+    #[cfg(feature = "std")]
+    retain_capture: bool,
+    /// Pulses of the last read, if retained.
+    #[cfg(feature = "std")]
+    capture: Option<Capture>,
+    /// Behavior when reading before the minimum interval has passed.
+    interval_policy: IntervalPolicy,
+    /// Minimum interval between two reads, by default the one of the model.
+    min_interval: Option<Duration>,
+    /// Timeouts ending an incomplete capture.
+    timeouts: CaptureTimeouts,
+    /// Start of the last transaction.
+    last_transaction: Option<Timestamp>,
+    /// Last successful reading, with the start of its transaction.
+    last_reading: Option<(DHT11Result, Timestamp)>,
 }
 
-/// Timeout duration for collecting input during sensor communication.
-const TIMEOUT_DURATION: u128 = 200; // milliseconds
+/// Behavior of a `DHT11Controller` when reading before the minimum interval between two reads
+/// of the sensor has passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntervalPolicy {
+    /// Blocks until the sensor is ready.
+    #[default]
+    Block,
+    /// Returns the last successful reading with its age, or `DHT11Error::TooSoon` if there is
+    /// none.
+    Cached,
+    /// Returns `DHT11Error::TooSoon`.
+    TooSoon,
+}
+
+/// Reading of a `DHT11Controller`, which can be cached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Reading of the sensor.
+    pub result: DHT11Result,
+    /// Time since the reading was made, zero for a new reading.
+    pub age: Duration,
+}
+
+/// Point in time, measured with the timestamps of the pin or the system clock.
+#[derive(Debug, Clone, Copy)]
+enum Timestamp {
+    Pin(Duration),
+    #[cfg(feature = "std")]
+    System(Instant),
+}
+
+/// Timeout duration for collecting input during sensor communication, the default deadline of a
+/// capture, which normally completes after about 5ms.
+const TIMEOUT_DURATION: u32 = 200; // milliseconds
+
+/// Default duration the line is driven high before the start signal, so the sensor sees a clean
+/// falling edge.
+const START_HIGH: Duration = Duration::from_millis(50);
+
+/// Default time waiting for the sensor to respond to the start signal (datasheet: 20-40µs).
+const NO_RESPONSE_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Timeouts ending a capture which did not complete, shared by the capture loops of the
+/// controllers. A complete capture ends once `PulseParser::is_complete()`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CaptureTimeouts {
+    /// Maximum duration of a capture, a safety net for incomplete transmissions.
+    pub(crate) deadline: Duration,
+    /// Maximum duration waiting for the sensor to respond to the start signal.
+    pub(crate) no_response: Duration,
+}
+
+impl Default for CaptureTimeouts {
+    fn default() -> Self {
+        CaptureTimeouts {
+            deadline: Duration::from_millis(TIMEOUT_DURATION as u64),
+            no_response: NO_RESPONSE_TIMEOUT,
+        }
+    }
+}
+
+impl CaptureTimeouts {
+    /// Time since the start of the capture after which it ends, the no-response timeout until
+    /// the sensor responded.
+    pub(crate) fn limit(&self, responded: bool) -> Duration {
+        if responded {
+            self.deadline
+        } else {
+            self.no_response.min(self.deadline)
+        }
+    }
+}
 
+/// Delays for a duration of any length, `DelayNs` only takes `u32` microseconds, which is a bit
+/// more than 71 minutes.
+pub(crate) fn delay_for<D: DelayNs>(delay: &mut D, duration: Duration) {
+    let mut micros = duration.as_micros();
+    while micros > 0 {
+        let chunk = micros.min(u32::MAX as u128) as u32;
+        delay.delay_us(chunk);
+        micros -= chunk as u128;
+    }
+}
+
+/// Start signal used when detecting the sensor model, the minimum of the DHT11 and below the
+/// maximum of the other models.
+const DETECTION_START_SIGNAL: Duration = Duration::from_millis(18);
+
+/// Start of a capture, used to measure time with the system clock.
+#[cfg(feature = "std")]
+type CaptureStart = Instant;
+/// Start of a capture, without `std` there is no system clock.
+#[cfg(not(feature = "std"))]
+type CaptureStart = ();
+
+/// Maximum number of pulses retained in a capture, bounding its memory. A transmission is 84
+/// pulses, the rest leaves room for glitches.
+#[cfg(feature = "std")]
+const MAX_CAPTURE_PULSES: usize = 256;
+
+/// Delay between two samples when collecting input without a clock, where the timeout is measured
+/// by counting samples instead.
+const SAMPLE_INTERVAL: u32 = 1; // microseconds
+
+#[cfg(feature = "rppal")]
 impl DHT11Controller {
-    /// Creates a new DHT11Controller instance with the specified GPIO pin.
-    pub fn new(dht_pin: u8) -> Result<DHT11Controller, Box<dyn Error>> {
-        let gpio = Gpio::new()?;
-        let controller = DHT11Controller {
-            dht_pin: gpio.get(dht_pin)?.into_io(Mode::Output),
-        };
-        return Ok(controller);
+    /// Creates a new DHT11Controller instance with the specified GPIO pin, errors accessing the GPIO
+    /// peripheral are reported as `DHT11Error::Backend`.
+    pub fn new(dht_pin: u8) -> Result<DHT11Controller, DHT11Error<rppal::gpio::Error>> {
+        DHT11Controller::from_gpio(&Gpio::new()?, dht_pin)
     }
 
-    /// Collects input levels from the DHT11 sensor during communication.
-    fn collect_input(&mut self) -> Vec<Level> {
-        let mut last = Level::Low;
-        let mut data: Vec<Level> = vec![];
-        let mut start_time = Instant::now();
+    /// Creates a new DHT11Controller instance with the specified pin of an already opened GPIO
+    /// peripheral, e.g. one shared with other devices. An existing `IoPin` can be passed to
+    /// `from_pin()` instead.
+    pub fn from_gpio(
+        gpio: &Gpio,
+        dht_pin: u8,
+    ) -> Result<DHT11Controller, DHT11Error<rppal::gpio::Error>> {
+        let dht_pin = gpio.get(dht_pin)?.into_io(Mode::Output);
+        Ok(DHT11Controller::from_pin(dht_pin))
+    }
 
-        loop {
-            let current = self.dht_pin.read();
-            data.push(current);
+    /// Creates a builder to configure the protocol timings of a controller, see
+    /// `DHT11ControllerBuilder`.
+    pub fn builder() -> DHT11ControllerBuilder {
+        DHT11ControllerBuilder::new()
+    }
+}
 
-            if last != current {
-                last = current;
-                start_time = Instant::now();
-            }
-            if start_time.elapsed().as_millis() > TIMEOUT_DURATION {
-                break;
-            }
+#[cfg(feature = "std")]
+impl<P: DHT11Pin> DHT11Controller<P> {
+    /// Creates a new DHT11Controller instance using an already configured pin backend.
+    pub fn from_pin(dht_pin: P) -> DHT11Controller<P> {
+        DHT11Controller::with_delay(dht_pin, StdDelay)
+    }
+}
+
+#[cfg(feature = "std")]
+impl DHT11Controller<SysfsPin> {
+    /// Creates a new DHT11Controller instance using the legacy sysfs GPIO interface.
+    pub fn from_sysfs(dht_pin: u32) -> std::io::Result<DHT11Controller<SysfsPin>> {
+        Ok(DHT11Controller::from_pin(SysfsPin::new(dht_pin)?))
+    }
+}
+
+impl<P: InputPin + OutputPin, D: DelayNs> DHT11Controller<HalPin<P>, D> {
+    /// Creates a new DHT11Controller instance from an `embedded-hal` open-drain IO pin and delay.
+    pub fn from_hal(dht_pin: P, delay: D) -> DHT11Controller<HalPin<P>, D> {
+        DHT11Controller::with_delay(HalPin::new(dht_pin), delay)
+    }
+}
+
+impl<P: DHT11Pin, D: DelayNs> DHT11Controller<P, D> {
+    /// Creates a new DHT11Controller instance using a pin backend and a delay provider.
+    pub fn with_delay(dht_pin: P, delay: D) -> DHT11Controller<P, D> {
+        DHT11Controller {
+            dht_pin,
+            delay,
+            model: Model::DHT11,
+            bit_decoding: None,
+            strategy: ThresholdStrategy::Auto,
+            start_high: START_HIGH,
+            start_signal: None,
+            bias: Bias::PullUp,
+            #[cfg(feature = "std")]
+            retain_capture: false,
+            #[cfg(feature = "std")]
+            capture: None,
+            interval_policy: IntervalPolicy::Block,
+            min_interval: None,
+            timeouts: CaptureTimeouts::default(),
+            last_transaction: None,
+            last_reading: None,
+        }
+    }
+
+    /// Sets the sensor model connected to the pin, by default a DHT11.
+    pub fn with_model(mut self, model: Model) -> DHT11Controller<P, D> {
+        self.model = model;
+        self
+    }
+
+    /// Returns the sensor model connected to the pin.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Consumes the controller, returning the underlying pin.
+    pub fn into_pin(self) -> P {
+        self.dht_pin
+    }
+
+    /// Returns the method used to classify the bits of the last successful reading.
+    pub fn last_bit_decoding(&self) -> Option<BitDecoding> {
+        self.bit_decoding
+    }
+
+    /// Sets the behavior when reading before the minimum interval between two reads has passed,
+    /// by default the read blocks until the sensor is ready.
+    ///
+    /// The interval is measured with the timestamps of the pin, or the system clock with `std`.
+    /// Without either it is not enforced.
+    pub fn with_interval_policy(mut self, policy: IntervalPolicy) -> DHT11Controller<P, D> {
+        self.interval_policy = policy;
+        self
+    }
+
+    /// Sets the minimum interval between two reads, by default the one of the sensor model (1s
+    /// for the DHT11, 2s for the others).
+    ///
+    /// Unlike `DHT11ControllerBuilder::with_min_interval()` the interval is not validated, e.g. to
+    /// read a simulated sensor without waiting.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> DHT11Controller<P, D> {
+        self.min_interval = Some(min_interval);
+        self
+    }
+
+    /// Sets the maximum duration of a capture, by default 200ms. The capture ends as soon as the
+    /// 40 bits and the trailing low are received, the deadline is a safety net for incomplete
+    /// transmissions.
+    ///
+    /// The deadline is not checked against the duration of a complete transmission, the builder
+    /// validates it.
+    pub fn with_deadline(mut self, deadline: Duration) -> DHT11Controller<P, D> {
+        self.timeouts.deadline = deadline;
+        self
+    }
+
+    /// Sets the maximum duration waiting for the sensor to respond to the start signal, by default
+    /// 10ms, after which the read fails with `DHT11Error::NoResponse`.
+    ///
+    /// The timeout is not validated, see `DHT11ControllerBuilder` for the datasheet limits.
+    pub fn with_no_response_timeout(mut self, timeout: Duration) -> DHT11Controller<P, D> {
+        self.timeouts.no_response = timeout;
+        self
+    }
+
+    /// Returns the minimum interval between two reads.
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+            .unwrap_or_else(|| self.model.min_interval())
+    }
+
+    /// Reads the sensor, respecting the minimum interval between two reads according to the
+    /// interval policy. Returns the reading with its age, which is only non-zero for a cached
+    /// reading.
+    pub fn read(&mut self) -> Result<Reading, DHT11Error<P::Error>> {
+        if let Some(reading) = self.wait_interval(true)? {
+            return Ok(reading);
         }
-        data
+        let start_signal = self
+            .start_signal
+            .unwrap_or_else(|| self.model.start_signal());
+        let parser = self.capture(start_signal)?;
+
+        let (result, bit_decoding) = decode_reading(&parser, self.model)?;
+        self.bit_decoding = Some(bit_decoding);
+        self.last_reading = self.last_transaction.map(|start| (result, start));
+        Ok(Reading {
+            result,
+            age: Duration::ZERO,
+        })
     }
 
-    /// Parses the lengths of pull-up and pull-down states in the DHT11 sensor communication data.
-    fn parse_data_pull_up_lengths(&mut self, data: &Vec<Level>) -> Vec<usize> {
-        // Represents different states in DHT11 sensor communication protocol
-        enum State {
-            InitPullDown,
-            InitPullUp,
-            DataFirstPullDown,
-            DataPullUp,
-            DataPullDown,
+    /// Sets whether the pulses received during each read are retained, by default they are not.
+    /// The capture of the last read is returned by `last_capture()`, e.g. to dump it to a file
+    /// after a failed read. The buffer is allocated once and reused, at most 256 pulses are
+    /// retained per read.
+    #[cfg(feature = "std")]
+    pub fn with_capture(mut self, retain: bool) -> DHT11Controller<P, D> {
+        self.retain_capture = retain;
+        if !retain {
+            self.capture = None;
         }
+        self
+    }
 
-        let mut state = State::InitPullDown;
-        let mut lengths: Vec<usize> = vec![];
-        let mut current_length: usize = 0;
+    /// Returns the pulses received during the last read, if retained.
+    #[cfg(feature = "std")]
+    pub fn last_capture(&self) -> Option<&Capture> {
+        self.capture.as_ref()
+    }
 
-        // Transitioning from states to other states to determine the lengths
-        for &current in data {
-            current_length += 1;
+    /// Reads the sensor and detects its model from the received frame, the reading is interpreted
+    /// using the detected model. If `lock` is set, the controller is switched to the detected
+    /// model for subsequent reads.
+    ///
+    /// The start signal used is short enough for all models, but long enough for the DHT11, unless
+    /// one was configured with the builder.
+    pub fn read_and_detect(
+        &mut self,
+        lock: bool,
+    ) -> Result<(DHT11Result, Detection), DHT11Error<P::Error>> {
+        self.wait_interval(false)?;
+        let parser = self.capture(self.start_signal.unwrap_or(DETECTION_START_SIGNAL))?;
+        let frame = parser.finish().map_err(DHT11Error::into_backend)?;
+        frame.check().map_err(DHT11Error::into_backend)?;
 
-            match state {
-                State::InitPullDown => {
-                    if current == Level::Low {
-                        state = State::InitPullUp;
-                    }
-                }
-                State::InitPullUp => {
-                    if current == Level::High {
-                        state = State::DataFirstPullDown;
-                    }
-                }
-                State::DataFirstPullDown => {
-                    if current == Level::Low {
-                        state = State::DataPullUp;
-                    }
-                }
-                State::DataPullUp => {
-                    if current == Level::High {
-                        current_length = 0;
-                        state = State::DataPullDown;
-                    }
-                }
-                State::DataPullDown => {
-                    if current == Level::Low {
-                        lengths.push(current_length);
-                        state = State::DataPullUp;
-                    }
-                }
-            }
+        let detection = model::detect_model(&frame.bytes);
+        let result = frame
+            .to_model_result(detection.model)
+            .map_err(DHT11Error::into_backend)?;
+        self.bit_decoding = Some(frame.bit_decoding);
+        self.last_reading = self.last_transaction.map(|start| (result, start));
+        if lock {
+            self.model = detection.model;
         }
-        lengths
+        Ok((result, detection))
     }
 
-    /// Calculates bits from the pull-up lengths in the DHT11 sensor communication data.
-    fn calculate_bits(&mut self, pull_up_lengths: &Vec<usize>) -> Vec<bool> {
-        let mut shortest_pull_up: usize = 1000;
-        let mut longest_pull_up: usize = 0;
+    /// Waits until the minimum interval since the last transaction has passed, or fails with
+    /// `DHT11Error::TooSoon`, depending on the interval policy. Returns the last reading if
+    /// `cached` readings are accepted and the policy allows it.
+    fn wait_interval(&mut self, cached: bool) -> Result<Option<Reading>, DHT11Error<P::Error>> {
+        let min_interval = self.min_interval();
+        let remaining = match self.last_transaction {
+            Some(last) => self
+                .elapsed(last)
+                .map(|elapsed| min_interval.saturating_sub(elapsed)),
+            None => None,
+        };
+        let remaining = match remaining {
+            Some(remaining) if !remaining.is_zero() => remaining,
+            _ => return Ok(None),
+        };
 
-        for length in pull_up_lengths {
-            if length < &shortest_pull_up {
-                shortest_pull_up = length.clone()
-            }
-            if length > &longest_pull_up {
-                longest_pull_up = length.clone()
+        match (self.interval_policy, self.last_reading) {
+            (IntervalPolicy::Block, _) => {
+                delay_for(&mut self.delay, remaining);
+                Ok(None)
             }
+            (IntervalPolicy::Cached, Some((result, start))) if cached => Ok(Some(Reading {
+                result,
+                age: self.elapsed(start).unwrap_or_default(),
+            })),
+            _ => Err(DHT11Error::TooSoon(remaining)),
         }
+    }
 
-        let halfway = shortest_pull_up + (longest_pull_up - shortest_pull_up) / 2;
-        let mut bits: Vec<bool> = vec![];
+    /// Returns the current point in time, using the timestamps of the pin if it provides them,
+    /// otherwise the system clock with `std`.
+    fn timestamp(&mut self) -> Option<Timestamp> {
+        let timestamp = self.dht_pin.timestamp().map(Timestamp::Pin);
+        #[cfg(feature = "std")]
+        let timestamp = timestamp.or_else(|| Some(Timestamp::System(Instant::now())));
+        timestamp
+    }
 
-        for length in pull_up_lengths {
-            let mut bit = false;
-            if length > &halfway {
-                bit = true;
-            }
-            bits.push(bit);
+    /// Returns the time elapsed since a point in time.
+    fn elapsed(&mut self, since: Timestamp) -> Option<Duration> {
+        match since {
+            Timestamp::Pin(since) => self
+                .dht_pin
+                .timestamp()
+                .map(|now| now.saturating_sub(since)),
+            #[cfg(feature = "std")]
+            Timestamp::System(since) => Some(since.elapsed()),
         }
-        bits
     }
 
-    /// Converts bits into bytes in the DHT11 sensor communication data.
-    fn bits_to_bytes(&mut self, bits: &Vec<bool>) -> Vec<usize> {
-        let mut bytes: Vec<usize> = vec![];
-        let mut byte: usize = 0;
+    /// Sends the start signal and captures the response of the sensor.
+    fn capture(&mut self, start_signal: Duration) -> Result<PulseParser, P::Error> {
+        self.last_transaction = self.timestamp();
+        // Sending power pulse to indicate a start signal for the sensor
+        self.dht_pin.set_mode(PinMode::Output)?;
+        self.dht_pin.set_high()?;
+        delay_for(&mut self.delay, self.start_high);
+        self.dht_pin.set_low()?;
+        delay_for(&mut self.delay, start_signal);
+
+        // Receiving data
+        self.dht_pin.set_mode(PinMode::Input)?;
+        self.dht_pin.set_bias(self.bias)?;
+        let parser = self.collect_input()?;
+        #[cfg(feature = "std")]
+        if let Some(capture) = self.capture.as_mut().filter(|_| self.retain_capture) {
+            capture.metadata.start_signal = Some(start_signal);
+        }
+        Ok(parser)
+    }
+
+    /// Returns the current time, using the timestamps of the pin if it provides them, otherwise
+    /// the time elapsed since `start` with `std`.
+    fn now(&mut self, start: &CaptureStart) -> Option<Duration> {
+        let timestamp = self.dht_pin.timestamp();
+        #[cfg(feature = "std")]
+        let timestamp = timestamp.or_else(|| Some(start.elapsed()));
+        #[cfg(not(feature = "std"))]
+        let _ = start;
+        timestamp
+    }
+
+    /// Collects input levels from the DHT11 sensor during communication, parsing the pull-up
+    /// lengths as the level changes, until the parser is complete or the capture times out.
+    ///
+    /// The time between two level changes is measured if a clock is available (`std` or a pin
+    /// providing timestamps), otherwise the number of samples is counted.
+    fn collect_input(&mut self) -> Result<PulseParser, P::Error> {
+        #[cfg(feature = "std")]
+        let start: CaptureStart = Instant::now();
+        #[cfg(not(feature = "std"))]
+        let start: CaptureStart = ();
 
-        for (i, bit) in bits.iter().enumerate() {
-            byte = byte << 1;
-            if *bit {
-                byte = byte | 1;
-            } else {
-                byte = byte | 0
+        let mut last = self.dht_pin.read()?;
+        let mut last_change = self.now(&start);
+        let begin = last_change;
+        let mut end;
+        let mut parser = PulseParser::new(match last_change {
+            Some(_) => PulseUnit::Microseconds,
+            None => PulseUnit::Samples,
+        })
+        .with_strategy(self.strategy);
+        #[cfg(feature = "std")]
+        if self.retain_capture {
+            // Reusing the buffer of the previous capture
+            let capture = self.capture.get_or_insert_with(|| {
+                let mut capture = Capture::new(parser.unit());
+                capture.pulses.reserve_exact(MAX_CAPTURE_PULSES);
+                capture
+            });
+            capture.metadata.timestamp = Some(std::time::SystemTime::now());
+            capture.unit = parser.unit();
+            capture.pulses.clear();
+        }
+        let mut samples: u32 = 0;
+        let mut total_samples: u32 = 0;
+        // Whether the sensor pulled the line low in response to the start signal, a line which is
+        // already low when released counts as a response as well
+        let mut responded = last == Level::Low;
+
+        loop {
+            let current = self.dht_pin.read()?;
+            let now = self.now(&start);
+            if now.is_none() {
+                self.delay.delay_us(SAMPLE_INTERVAL);
             }
-            if (i + 1) % 8 == 0 {
-                bytes.push(byte);
-                byte = 0;
+            end = now;
+            samples += 1;
+            total_samples = total_samples.saturating_add(1);
+
+            let length = match (now, last_change) {
+                (Some(now), Some(last_change)) => (now - last_change).as_micros() as u32,
+                _ => samples,
+            };
+            if last != current {
+                parser.feed_pulse(last, length);
+                self.record(last, length);
+                last = current;
+                last_change = now;
+                samples = 0;
+                responded |= current == Level::Low;
+
+                if parser.is_complete() {
+                    break;
+                }
             }
+
+            let elapsed = match (now, begin) {
+                (Some(now), Some(begin)) => now.saturating_sub(begin),
+                _ => Duration::from_micros(total_samples as u64 * SAMPLE_INTERVAL as u64),
+            };
+            if elapsed > self.timeouts.limit(responded) {
+                // The level held until the timeout completes the waveform of the capture
+                parser.feed_pulse(last, length);
+                self.record(last, length);
+                break;
+            }
+        }
+        #[cfg(feature = "std")]
+        if let Some(capture) = self.capture.as_mut().filter(|_| self.retain_capture) {
+            // Mean time between two reads of the pin, without a clock the delay between them
+            capture.metadata.sample_period = Some(match (end, begin) {
+                (Some(end), Some(begin)) => end.saturating_sub(begin) / total_samples.max(1),
+                _ => Duration::from_micros(SAMPLE_INTERVAL as u64),
+            });
         }
-        bytes
+        #[cfg(not(feature = "std"))]
+        let _ = end;
+        Ok(parser)
     }
 
-    /// Calculates the checksum from the bytes in the DHT11 sensor communication data.
-    fn calculate_checksum(&mut self, bytes: &Vec<usize>) -> usize {
-        bytes[0] + bytes[1] + bytes[2] + bytes[3] & 255
+    /// Appends a pulse to the capture of the current read, if retained. The capture is bounded
+    /// to `MAX_CAPTURE_PULSES`, so it never reallocates.
+    fn record(&mut self, level: Level, length: u32) {
+        #[cfg(feature = "std")]
+        if let Some(capture) = self.capture.as_mut().filter(|_| self.retain_capture) {
+            if capture.pulses.len() < MAX_CAPTURE_PULSES {
+                capture.pulses.push((level, length));
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = (level, length);
     }
 }
 
 /// Enum representing possible errors during DHT11 sensor communication.
+///
+/// `E` is the error type of the pin backend, which is `Infallible` for the rppal `IoPin`.
+///
+/// The decoding errors carry the `Evidence` captured during the read, the received bit count,
+/// pull-up lengths and raw bytes.
 #[derive(Debug)]
-pub enum DHT11Error {
-    /// Bit count mismatch (4 byte data + 1 byte checksum)
-    MissingData,
+pub enum DHT11Error<E = Infallible> {
+    /// The sensor did not pull the line low after the start signal
+    NoResponse,
+    /// The sensor responded, but the data transmission did not start before the timeout
+    PreambleTimeout(Evidence),
+    /// Fewer than 40 bits (4 byte data + 1 byte checksum) were received before the timeout
+    TooFewBits(Evidence),
+    /// More than 40 bits (4 byte data + 1 byte checksum) were received, only reported when
+    /// decoding offline, as a live capture ends after the 40th bit
+    TooManyBits(Evidence),
     /// The calculated checksum (4 bytes) does not match the 1 byte validation checksum (last 1 byte)
+    InvalidChecksum {
+        /// Checksum calculated from the 4 data bytes
+        expected: u8,
+        /// Validation checksum received from the sensor
+        got: u8,
+        evidence: Evidence,
+    },
+    /// The decoded values are outside of the measuring range of the sensor model
+    OutOfRange(Evidence),
+    /// The sensor reported invalid data without further details
+    InvalidData,
+    /// The minimum interval since the last read has not passed, the sensor is ready after the
+    /// remaining duration
+    TooSoon(Duration),
+    /// An operation on the pin backend failed
+    Backend(E),
+}
+
+impl<E> DHT11Error<E> {
+    /// Evidence captured during the read, if the error occurred while decoding.
+    pub fn evidence(&self) -> Option<&Evidence> {
+        match self {
+            Self::PreambleTimeout(evidence)
+            | Self::TooFewBits(evidence)
+            | Self::TooManyBits(evidence)
+            | Self::InvalidChecksum { evidence, .. }
+            | Self::OutOfRange(evidence) => Some(evidence),
+            Self::NoResponse | Self::InvalidData | Self::TooSoon(_) | Self::Backend(_) => None,
+        }
+    }
+
+    /// Kind of the error, without the captured evidence.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NoResponse => ErrorKind::NoResponse,
+            Self::PreambleTimeout(_) => ErrorKind::PreambleTimeout,
+            Self::TooFewBits(_) => ErrorKind::TooFewBits,
+            Self::TooManyBits(_) => ErrorKind::TooManyBits,
+            Self::InvalidChecksum { .. } => ErrorKind::InvalidChecksum,
+            Self::OutOfRange(_) => ErrorKind::OutOfRange,
+            Self::InvalidData => ErrorKind::InvalidData,
+            Self::TooSoon(_) => ErrorKind::TooSoon,
+            Self::Backend(_) => ErrorKind::Backend,
+        }
+    }
+}
+
+/// Kind of a `DHT11Error`, e.g. to select the errors a `RetryingReader` retries on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// `DHT11Error::NoResponse`
+    NoResponse,
+    /// `DHT11Error::PreambleTimeout`
+    PreambleTimeout,
+    /// `DHT11Error::TooFewBits`
+    TooFewBits,
+    /// `DHT11Error::TooManyBits`
+    TooManyBits,
+    /// `DHT11Error::InvalidChecksum`
     InvalidChecksum,
+    /// `DHT11Error::OutOfRange`
+    OutOfRange,
+    /// `DHT11Error::InvalidData`
+    InvalidData,
+    /// `DHT11Error::TooSoon`
+    TooSoon,
+    /// `DHT11Error::Backend`
+    Backend,
 }
 
-impl std::fmt::Display for DHT11Error {
+impl<E: fmt::Display> fmt::Display for DHT11Error<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::MissingData => write!(f, "Bit count mismatch (4 byte data + 1 byte checksum)"),
-            Self::InvalidChecksum => write!(f, "The calculated checksum (4 bytes) does not match the 1 byte validation checksum (last 1 byte)"),
+            Self::NoResponse => write!(f, "No response from the sensor"),
+            Self::PreambleTimeout(_) => write!(f, "Timed out waiting for the data transmission"),
+            Self::TooFewBits(evidence) => {
+                write!(f, "Too few bits received ({} of 40)", evidence.bit_count)
+            }
+            Self::TooManyBits(evidence) => {
+                write!(f, "Too many bits received ({} of 40)", evidence.bit_count)
+            }
+            Self::InvalidChecksum { expected, got, .. } => write!(
+                f,
+                "Invalid checksum (expected {:#04x}, got {:#04x})",
+                expected, got
+            ),
+            Self::OutOfRange(evidence) => match evidence.bytes {
+                Some(bytes) => write!(f, "Reading out of range (bytes {:02x?})", bytes),
+                None => write!(f, "Reading out of range"),
+            },
+            Self::InvalidData => write!(f, "The sensor reported invalid data"),
+            Self::TooSoon(remaining) => write!(
+                f,
+                "Read too soon, the sensor is ready in {} ms",
+                remaining.as_millis()
+            ),
+            Self::Backend(err) => write!(f, "Pin backend error: {}", err),
         }
     }
 }
 
-impl Error for DHT11Error {}
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> Error for DHT11Error<E> {}
 
-impl Sensor<DHT11Result, DHT11Error> for DHT11Controller {
-    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error> {
-        // Sending power pulse to indicate a start signal for the sensor
-        self.dht_pin.set_mode(Mode::Output);
-        self.dht_pin.set_high();
-        thread::sleep(Duration::from_millis(50));
-        self.dht_pin.set_low();
-        thread::sleep(Duration::from_millis(20));
+impl DHT11Error {
+    /// Converts a decoding error, which never is a backend error, into the error of a backend.
+    pub(crate) fn into_backend<E>(self) -> DHT11Error<E> {
+        match self {
+            Self::NoResponse => DHT11Error::NoResponse,
+            Self::PreambleTimeout(evidence) => DHT11Error::PreambleTimeout(evidence),
+            Self::TooFewBits(evidence) => DHT11Error::TooFewBits(evidence),
+            Self::TooManyBits(evidence) => DHT11Error::TooManyBits(evidence),
+            Self::InvalidChecksum {
+                expected,
+                got,
+                evidence,
+            } => DHT11Error::InvalidChecksum {
+                expected,
+                got,
+                evidence,
+            },
+            Self::OutOfRange(evidence) => DHT11Error::OutOfRange(evidence),
+            Self::InvalidData => DHT11Error::InvalidData,
+            Self::TooSoon(remaining) => DHT11Error::TooSoon(remaining),
+            Self::Backend(never) => match never {},
+        }
+    }
+}
 
-        // Receiving data
-        self.dht_pin.set_mode(Mode::Input);
-        self.dht_pin.set_bias(Bias::PullUp);
-        let data = self.collect_input();
-        let pull_up_lengths: Vec<usize> = self.parse_data_pull_up_lengths(&data);
+impl<E> From<E> for DHT11Error<E> {
+    fn from(err: E) -> Self {
+        Self::Backend(err)
+    }
+}
 
-        if pull_up_lengths.len() != 40 {
-            // Bit count mismatch occurred
-            return Err(DHT11Error::MissingData);
-        }
+impl<P: DHT11Pin, D: DelayNs> Sensor<DHT11Result, DHT11Error<P::Error>> for DHT11Controller<P, D> {
+    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error<P::Error>> {
+        self.read().map(|reading| reading.result)
+    }
+}
+
+/// Decodes the pull-up lengths collected by a parser into a reading of the sensor model and the
+/// method used to classify its bits.
+pub(crate) fn decode_reading<E>(
+    parser: &PulseParser,
+    model: Model,
+) -> Result<(DHT11Result, BitDecoding), DHT11Error<E>> {
+    let frame = parser.finish().map_err(DHT11Error::into_backend)?;
+    let result = frame
+        .to_model_result(model)
+        .map_err(DHT11Error::into_backend)?;
+    Ok((result, frame.bit_decoding))
+}
 
-        let bits = self.calculate_bits(&pull_up_lengths);
-        let bytes = self.bits_to_bytes(&bits);
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sim::{Fault, SimulatedDelay, SimulatedPin};
 
-        let checksum = self.calculate_checksum(&bytes);
-        if bytes[4] != checksum {
-            // The checksum does not match the validation checksum
-            return Err(DHT11Error::InvalidChecksum);
-        }
+    /// 45% humidity and 21.3°C with a valid checksum.
+    const BYTES: [u8; 5] = [45, 0, 21, 3, 69];
 
-        // Data was valid
-        // bytes[0] : humidity    [integer]
-        // bytes[1] : humidity    [decimal]
-        // bytes[2] : temperature [integer]
-        // bytes[3] : temperature [decimal]
+    /// Start high and start signal of a default DHT11 controller.
+    const START: Duration = Duration::from_millis(70);
 
-        let humidity = bytes[0] as f64 + (bytes[1] as f64 / 10.0);
-        let temperature = bytes[2] as f64 + (bytes[3] as f64 / 10.0);
+    /// Reads of the pin after the end of the capture, each advancing the virtual clock by 1µs.
+    const SLACK: Duration = Duration::from_micros(5);
 
-        Ok(DHT11Result {
-            temperature,
-            humidity,
-        })
+    fn controller(pin: SimulatedPin) -> DHT11Controller<SimulatedPin, SimulatedDelay> {
+        let delay = pin.delay();
+        DHT11Controller::with_delay(pin, delay)
+    }
+
+    #[test]
+    fn ends_after_trailing_low() {
+        let pin = SimulatedPin::new(BYTES);
+        let end = START + pin.response_duration();
+        let mut controller = controller(pin);
+
+        controller.read().unwrap();
+        let now = controller.into_pin().now();
+        assert!(now >= end && now <= end + SLACK, "ended at {:?}", now);
+    }
+
+    #[test]
+    fn honors_no_response_timeout() {
+        let mut controller = controller(SimulatedPin::new(BYTES).with_fault(Fault::NoResponse))
+            .with_no_response_timeout(Duration::from_millis(3));
+
+        let err = controller.read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NoResponse);
+        let end = START + Duration::from_millis(3);
+        let now = controller.into_pin().now();
+        assert!(now >= end && now <= end + SLACK, "ended at {:?}", now);
+    }
+
+    #[test]
+    fn honors_deadline() {
+        let mut controller =
+            controller(SimulatedPin::new(BYTES).with_fault(Fault::DroppedBits(20)))
+                .with_deadline(Duration::from_millis(20));
+
+        let err = controller.read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooFewBits);
+        let end = START + Duration::from_millis(20);
+        let now = controller.into_pin().now();
+        assert!(now >= end && now <= end + SLACK, "ended at {:?}", now);
+    }
+
+    #[test]
+    fn locks_detected_model() {
+        // 65.2% humidity and -10.1°C sent by a DHT22
+        let bytes = [0x02, 0x8C, 0x80, 0x65, 0x73];
+        let mut controller = controller(SimulatedPin::new(bytes).with_model(Model::DHT22));
+
+        let (result, detection) = controller.read_and_detect(false).unwrap();
+        assert_eq!(detection.model, Model::DHT22);
+        assert_eq!(result.humidity, 65.2);
+        assert_eq!(result.temperature, -10.1);
+        assert_eq!(controller.model(), Model::DHT11);
+
+        controller.read_and_detect(true).unwrap();
+        assert_eq!(controller.model(), Model::DHT22);
+        let reading = controller.read().unwrap();
+        assert_eq!(reading.result, result);
+    }
+
+    #[test]
+    fn blocks_until_min_interval() {
+        let pin = SimulatedPin::new(BYTES);
+        let response = pin.response_duration();
+        let mut controller = controller(pin);
+
+        controller.read().unwrap();
+        let reading = controller.read().unwrap();
+        assert_eq!(reading.age, Duration::ZERO);
+        // The second transaction starts once the minimum interval since the first one passed
+        let end = Duration::from_secs(1) + START + response;
+        let now = controller.dht_pin.now();
+        assert!(now >= end && now <= end + SLACK, "ended at {:?}", now);
+    }
+
+    #[test]
+    fn blocks_beyond_delay_range() {
+        // Longer than the `u32` microseconds a single delay takes
+        let min_interval = Duration::from_secs(5000);
+        let mut controller = controller(SimulatedPin::new(BYTES)).with_min_interval(min_interval);
+
+        controller.read().unwrap();
+        controller.read().unwrap();
+        assert!(controller.dht_pin.now() >= min_interval + START);
+    }
+
+    #[test]
+    fn returns_cached_reading() {
+        let mut cached =
+            controller(SimulatedPin::new(BYTES)).with_interval_policy(IntervalPolicy::Cached);
+
+        let reading = cached.read().unwrap();
+        let now = cached.dht_pin.now();
+        let again = cached.read().unwrap();
+        assert_eq!(again.result, reading.result);
+        // The age is measured from the start of the transaction, without reading the sensor
+        assert_eq!(again.age, now);
+        assert_eq!(cached.dht_pin.now(), now);
+
+        // Without a successful reading there is nothing to return
+        let mut failing = controller(SimulatedPin::new(BYTES).with_fault(Fault::NoResponse))
+            .with_interval_policy(IntervalPolicy::Cached);
+        assert_eq!(failing.read().unwrap_err().kind(), ErrorKind::NoResponse);
+        failing.dht_pin.set_fault(None);
+        assert_eq!(failing.read().unwrap_err().kind(), ErrorKind::TooSoon);
+    }
+
+    #[test]
+    fn fails_too_soon() {
+        let mut controller =
+            controller(SimulatedPin::new(BYTES)).with_interval_policy(IntervalPolicy::TooSoon);
+
+        controller.read().unwrap();
+        let now = controller.dht_pin.now();
+        match controller.read().unwrap_err() {
+            DHT11Error::TooSoon(remaining) => assert_eq!(remaining, Duration::from_secs(1) - now),
+            err => panic!("unexpected error: {}", err),
+        }
+        // Driving the start signal would have advanced the clock
+        assert_eq!(controller.dht_pin.now(), now);
     }
 }
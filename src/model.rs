@@ -0,0 +1,282 @@
+use crate::decode::DATA_BYTES;
+use crate::DHT11Result;
+use core::time::Duration;
+
+/// Sensor models of the DHT family, which share the single-wire protocol but differ in the start
+/// signal and the interpretation of the data bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Model {
+    /// DHT11, sends humidity and temperature as integer and decimal bytes. Newer revisions
+    /// send sub-zero temperatures with the sign in the highest bit of the temperature decimal
+    /// byte, which is decoded accordingly.
+    #[default]
+    DHT11,
+    /// DHT11 using the legacy interpretation, adding the whole temperature decimal byte as tenths
+    /// without checking for a sign bit.
+    DHT11Legacy,
+    /// DHT22 / AM2302, sends humidity and temperature as 16-bit tenths, with the sign of the
+    /// temperature in the highest bit.
+    DHT22,
+    /// DHT21 / AM2301, sends the data in the same format as the DHT22.
+    DHT21,
+    /// DHT12 in single-bus mode, sends humidity and temperature as integer and decimal bytes,
+    /// with the sign of the temperature in the highest bit of its decimal byte.
+    DHT12,
+}
+
+impl Model {
+    /// Duration the host pulls the line low to send the start signal.
+    pub fn start_signal(&self) -> Duration {
+        match self {
+            Model::DHT11 | Model::DHT11Legacy => Duration::from_millis(20),
+            Model::DHT22 | Model::DHT21 | Model::DHT12 => Duration::from_millis(1),
+        }
+    }
+
+    /// Minimum interval between two reads, according to the datasheet. Reading more often returns
+    /// stale data or no response at all.
+    pub fn min_interval(&self) -> Duration {
+        match self {
+            Model::DHT11 | Model::DHT11Legacy => Duration::from_secs(1),
+            Model::DHT22 | Model::DHT21 | Model::DHT12 => Duration::from_secs(2),
+        }
+    }
+
+    /// Minimum duration of the start signal the sensor reacts to, according to the datasheet.
+    pub fn min_start_signal(&self) -> Duration {
+        match self {
+            Model::DHT11 | Model::DHT11Legacy => Duration::from_millis(18),
+            Model::DHT22 => Duration::from_millis(1),
+            Model::DHT21 | Model::DHT12 => Duration::from_micros(800),
+        }
+    }
+
+    /// Maximum duration of the start signal, according to the datasheet. Longer start signals
+    /// may be missed, as the sensor can time out waiting for the host to release the line.
+    pub fn max_start_signal(&self) -> Duration {
+        match self {
+            Model::DHT11 | Model::DHT11Legacy => Duration::from_millis(30),
+            Model::DHT22 | Model::DHT21 | Model::DHT12 => Duration::from_millis(20),
+        }
+    }
+
+    /// Whether the reading is inside the measuring range of the model.
+    pub fn is_in_range(&self, result: &DHT11Result) -> bool {
+        let temperature = match self {
+            Model::DHT11 | Model::DHT11Legacy | Model::DHT12 => -20.0..=60.0,
+            Model::DHT22 | Model::DHT21 => -40.0..=80.0,
+        };
+        (0.0..=100.0).contains(&result.humidity) && temperature.contains(&result.temperature)
+    }
+
+    /// Humidity in percentage encoded in the frame bytes.
+    pub fn humidity(&self, bytes: &[u8; DATA_BYTES]) -> f64 {
+        match self {
+            // bytes[0] : humidity    [integer]
+            // bytes[1] : humidity    [decimal]
+            Model::DHT11 | Model::DHT11Legacy | Model::DHT12 => {
+                bytes[0] as f64 + (bytes[1] as f64 / 10.0)
+            }
+            // bytes[0..2] : humidity [tenths]
+            Model::DHT22 | Model::DHT21 => u16::from_be_bytes([bytes[0], bytes[1]]) as f64 / 10.0,
+        }
+    }
+
+    /// Temperature in degrees Celsius encoded in the frame bytes.
+    pub fn temperature(&self, bytes: &[u8; DATA_BYTES]) -> f64 {
+        match self {
+            // bytes[2] : temperature [integer]
+            // bytes[3] : temperature [decimal]
+            Model::DHT11Legacy => bytes[2] as f64 + (bytes[3] as f64 / 10.0),
+            // bytes[2] : temperature [integer]
+            // bytes[3] : temperature [sign bit + decimal]
+            Model::DHT11 | Model::DHT12 => {
+                let value = bytes[2] as f64 + ((bytes[3] & 0x7F) as f64 / 10.0);
+                if bytes[3] & 0x80 != 0 {
+                    -value
+                } else {
+                    value
+                }
+            }
+            // bytes[2..4] : temperature [sign bit + tenths]
+            Model::DHT22 | Model::DHT21 => {
+                let tenths = u16::from_be_bytes([bytes[2] & 0x7F, bytes[3]]) as f64 / 10.0;
+                if bytes[2] & 0x80 != 0 {
+                    -tenths
+                } else {
+                    tenths
+                }
+            }
+        }
+    }
+}
+
+/// Sensor model detected from a frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// Most likely model. The DHT21 can not be distinguished from the DHT22, so it is reported as
+    /// DHT22.
+    pub model: Model,
+    /// Confidence of the detection, from `0.0` (no model fits) to `1.0` (only this model fits).
+    pub confidence: f64,
+}
+
+/// Scores how well the frame bytes fit the value ranges and resolution of a DHT11.
+fn dht11_score(bytes: &[u8; DATA_BYTES]) -> f64 {
+    // 0-100% humidity with 1% resolution, -20-60°C temperature with decimals up to 9
+    if bytes[0] > 100 || bytes[1] > 9 || bytes[2] > 60 || bytes[3] & 0x7F > 9 {
+        return 0.0;
+    }
+    let mut score = if bytes[1] == 0 { 1.0 } else { 0.5 };
+    if bytes[0] < 5 {
+        score *= 0.2;
+    }
+    score
+}
+
+/// Scores how well the frame bytes fit the value ranges of a DHT12.
+fn dht12_score(bytes: &[u8; DATA_BYTES]) -> f64 {
+    // 0-100% humidity, -20-60°C temperature, both with decimals up to 9
+    if bytes[0] > 100 || bytes[1] > 9 || bytes[2] > 60 || bytes[3] & 0x7F > 9 {
+        return 0.0;
+    }
+    // Only a humidity decimal distinguishes it from a DHT11
+    let mut score = if bytes[1] != 0 { 0.8 } else { 0.5 };
+    if bytes[0] < 5 {
+        score *= 0.2;
+    }
+    score
+}
+
+/// Scores how well the frame bytes fit the value ranges of a DHT22.
+fn dht22_score(bytes: &[u8; DATA_BYTES]) -> f64 {
+    // 0-100.0% humidity, -40-80.0°C temperature
+    let humidity = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let temperature = u16::from_be_bytes([bytes[2] & 0x7F, bytes[3]]);
+    if humidity > 1000 || temperature > 800 || (bytes[2] & 0x80 != 0 && temperature > 400) {
+        return 0.0;
+    }
+    1.0
+}
+
+/// Heuristically detects the sensor model that sent the frame bytes, from the value ranges and
+/// the use of the decimal bytes of each model.
+pub fn detect_model(bytes: &[u8; DATA_BYTES]) -> Detection {
+    let scores = [
+        (Model::DHT11, dht11_score(bytes)),
+        (Model::DHT22, dht22_score(bytes)),
+        (Model::DHT12, dht12_score(bytes)),
+    ];
+
+    let total: f64 = scores.iter().map(|&(_, score)| score).sum();
+    let (model, best) = scores
+        .iter()
+        .copied()
+        .fold((Model::DHT11, 0.0), |best, score| {
+            if score.1 > best.1 {
+                score
+            } else {
+                best
+            }
+        });
+
+    Detection {
+        model,
+        confidence: if total > 0.0 { best / total } else { 0.0 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frame bytes (without checksum) with the expected humidity and temperature per model.
+    const FRAMES: [(Model, [u8; 4], f64, f64); 12] = [
+        (Model::DHT11, [45, 0, 21, 3], 45.0, 21.3),
+        (Model::DHT11, [45, 0, 0, 0], 45.0, 0.0),
+        // Sign in the highest bit of the temperature decimal byte
+        (Model::DHT11, [45, 0, 5, 0x83], 45.0, -5.3),
+        (Model::DHT11, [45, 0, 0, 0x81], 45.0, -0.1),
+        (Model::DHT11Legacy, [45, 0, 21, 3], 45.0, 21.3),
+        // The whole decimal byte is added as tenths
+        (Model::DHT11Legacy, [45, 0, 5, 0x83], 45.0, 18.1),
+        (Model::DHT22, [0x02, 0x8C, 0x01, 0x5F], 65.2, 35.1),
+        // Sign in the highest bit of the 16-bit temperature
+        (Model::DHT22, [0x02, 0x8C, 0x80, 0x65], 65.2, -10.1),
+        (Model::DHT22, [0x03, 0xE8, 0x81, 0x90], 100.0, -40.0),
+        (Model::DHT21, [0x02, 0x8C, 0x80, 0x65], 65.2, -10.1),
+        (Model::DHT12, [56, 8, 21, 3], 56.8, 21.3),
+        (Model::DHT12, [56, 8, 5, 0x83], 56.8, -5.3),
+    ];
+
+    #[test]
+    fn interprets_frames() {
+        for (model, data, humidity, temperature) in FRAMES {
+            let bytes = [data[0], data[1], data[2], data[3], 0];
+            assert!(
+                (model.humidity(&bytes) - humidity).abs() < 1e-9,
+                "{:?} {:?}: humidity {}",
+                model,
+                data,
+                model.humidity(&bytes)
+            );
+            assert!(
+                (model.temperature(&bytes) - temperature).abs() < 1e-9,
+                "{:?} {:?}: temperature {}",
+                model,
+                data,
+                model.temperature(&bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn checks_range() {
+        let result = |humidity, temperature| DHT11Result {
+            temperature,
+            humidity,
+        };
+        assert!(Model::DHT11.is_in_range(&result(45.0, -20.0)));
+        assert!(!Model::DHT11.is_in_range(&result(45.0, -30.0)));
+        assert!(Model::DHT22.is_in_range(&result(45.0, -40.0)));
+        assert!(!Model::DHT22.is_in_range(&result(100.1, 20.0)));
+    }
+
+    /// Frame bytes (without checksum) with the expected detected model and confidence.
+    #[cfg(feature = "std")]
+    const DETECTIONS: [([u8; 4], Model, f64); 8] = [
+        // Also fit a DHT12 without humidity decimal
+        ([45, 0, 21, 3], Model::DHT11, 1.0 / 1.5),
+        ([45, 0, 5, 0x83], Model::DHT11, 1.0 / 1.5),
+        // Only the humidity decimal tells a DHT12 from a DHT11
+        ([56, 8, 21, 3], Model::DHT12, 0.8 / 1.3),
+        ([56, 8, 5, 0x83], Model::DHT12, 0.8 / 1.3),
+        // Humidity and temperature as 16-bit tenths only fit a DHT22
+        ([0x02, 0x8C, 0x01, 0x5F], Model::DHT22, 1.0),
+        ([0x02, 0x8C, 0x80, 0x65], Model::DHT22, 1.0),
+        // A low humidity byte fits all models, the DHT11 and DHT12 unlikely
+        ([2, 0, 1, 3], Model::DHT22, 1.0 / 1.3),
+        // No model fits
+        ([200, 200, 200, 200], Model::DHT11, 0.0),
+    ];
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn detects_encoded_frames() {
+        use crate::decode::decode_pulses;
+        use crate::encode::{with_checksum, Encoder};
+
+        for (data, model, confidence) in DETECTIONS {
+            let pulses = Encoder::new().pulses(&with_checksum(data));
+            let frame = decode_pulses(&pulses).unwrap();
+            let detection = detect_model(&frame.bytes);
+            assert_eq!(detection.model, model, "{:?}", data);
+            assert!(
+                (detection.confidence - confidence).abs() < 1e-9,
+                "{:?}: confidence {}",
+                data,
+                detection.confidence
+            );
+        }
+    }
+}
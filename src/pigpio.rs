@@ -0,0 +1,322 @@
+use crate::decode::{BitDecoding, PulseParser, PulseUnit};
+use crate::pin::Level;
+use crate::{decode_reading, CaptureTimeouts, DHT11Error, DHT11Result, Model, Sensor};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Subset of the pigpiod socket interface, see https://abyz.me.uk/rpi/pigpio/sif.html
+
+const PI_CMD_MODES: u32 = 0;
+const PI_CMD_PUD: u32 = 2;
+const PI_CMD_WRITE: u32 = 4;
+const PI_CMD_NB: u32 = 19;
+const PI_CMD_NP: u32 = 20;
+const PI_CMD_NC: u32 = 21;
+const PI_CMD_NOIB: u32 = 99;
+
+const PI_INPUT: u32 = 0;
+const PI_OUTPUT: u32 = 1;
+const PI_PUD_UP: u32 = 2;
+
+/// Size of a notification report (seqno: u16, flags: u16, tick: u32, level: u32).
+const REPORT_SIZE: usize = 12;
+
+/// Highest GPIO in the bank of the notification reports, which carry the levels of GPIO 0-31.
+const MAX_GPIO: u32 = 31;
+
+/// Default address of the pigpio daemon.
+const DEFAULT_ADDRESS: &str = "localhost:8888";
+
+/// Struct representing a DHT11 sensor connected to a GPIO held by the pigpio daemon (`pigpiod`).
+///
+/// The start signal is sent through the command socket and the sensor response is received as
+/// level change notifications, timestamped with the DMA-timed ticks (in microseconds) of the
+/// daemon.
+pub struct PigpioController {
+    /// Socket used to send commands.
+    command: TcpStream,
+    /// Socket receiving the notification reports.
+    notify: TcpStream,
+    /// Handle of the notification channel opened on `notify`.
+    handle: u32,
+    /// Broadcom number of the GPIO connected to the DHT11 sensor.
+    gpio: u32,
+    /// Sensor model, determining the start signal and the interpretation of the data.
+    model: Model,
+    /// Method used to classify the bits of the last successful reading.
+    bit_decoding: Option<BitDecoding>,
+    /// Timeouts ending an incomplete capture.
+    timeouts: CaptureTimeouts,
+}
+
+/// Sends a command to the daemon and returns its result.
+fn command(stream: &mut TcpStream, cmd: u32, p1: u32, p2: u32) -> io::Result<u32> {
+    let mut buffer = [0u8; 16];
+    buffer[0..4].copy_from_slice(&cmd.to_le_bytes());
+    buffer[4..8].copy_from_slice(&p1.to_le_bytes());
+    buffer[8..12].copy_from_slice(&p2.to_le_bytes());
+    stream.write_all(&buffer)?;
+    stream.read_exact(&mut buffer)?;
+
+    let result = i32::from_le_bytes([buffer[12], buffer[13], buffer[14], buffer[15]]);
+    if result < 0 {
+        return Err(io::Error::other(format!(
+            "pigpio command {} failed with error {}",
+            cmd, result
+        )));
+    }
+    Ok(result as u32)
+}
+
+impl PigpioController {
+    /// Connects to the pigpio daemon on `localhost:8888` for the sensor on the specified GPIO.
+    pub fn new(gpio: u32) -> io::Result<PigpioController> {
+        PigpioController::connect(DEFAULT_ADDRESS, gpio)
+    }
+
+    /// Connects to the pigpio daemon at `address` for the sensor on the specified GPIO, which has
+    /// to be one of GPIO 0-31, the levels of the others are not notified.
+    pub fn connect<A: ToSocketAddrs>(address: A, gpio: u32) -> io::Result<PigpioController> {
+        if gpio > MAX_GPIO {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("GPIO {} is not in the notified bank 0-31", gpio),
+            ));
+        }
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address"))?;
+        let command = TcpStream::connect(address)?;
+        let mut notify = TcpStream::connect(address)?;
+        command.set_nodelay(true)?;
+
+        let handle = self::command(&mut notify, PI_CMD_NOIB, 0, 0)?;
+
+        Ok(PigpioController {
+            command,
+            notify,
+            handle,
+            gpio,
+            model: Model::DHT11,
+            bit_decoding: None,
+            timeouts: CaptureTimeouts::default(),
+        })
+    }
+
+    /// Sets the sensor model connected to the line, by default a DHT11.
+    pub fn with_model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Returns the sensor model connected to the line.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Returns the method used to classify the bits of the last successful reading.
+    pub fn last_bit_decoding(&self) -> Option<BitDecoding> {
+        self.bit_decoding
+    }
+
+    /// Sets the maximum duration of a capture, by default 200ms. A complete transmission ends the
+    /// capture before it.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.timeouts.deadline = deadline;
+        self
+    }
+
+    /// Sets the maximum duration waiting for the sensor to pull the GPIO low in response to the
+    /// start signal, by default 10ms, after which the read fails with `DHT11Error::NoResponse`.
+    pub fn with_no_response_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.no_response = timeout;
+        self
+    }
+
+    /// Discards reports left over from a previous read.
+    fn drain_reports(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; 256];
+        self.notify.set_nonblocking(true)?;
+        let result = loop {
+            match self.notify.read(&mut buffer) {
+                Ok(0) => break Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        self.notify.set_nonblocking(false)?;
+        result
+    }
+
+    /// Collects the notification reports until the parser is complete, feeding the tick
+    /// difference between two changes of the GPIO into the parser as the length of a pull-up or
+    /// pull-down in microseconds, without allocating. The sensor responded once it pulled the
+    /// GPIO low.
+    fn collect_edges(&mut self, parser: &mut PulseParser) -> io::Result<()> {
+        // Level of the GPIO after the last change with its tick
+        let mut last: Option<(Level, u32)> = None;
+        let mut buffer = [0u8; REPORT_SIZE * 64];
+        let mut filled = 0;
+        // Whether the sensor pulled the GPIO low in response to the start signal
+        let mut responded = false;
+        let begin = Instant::now();
+
+        loop {
+            let remaining = self
+                .timeouts
+                .limit(responded)
+                .saturating_sub(begin.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            self.notify.set_read_timeout(Some(remaining))?;
+            match self.notify.read(&mut buffer[filled..]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => filled += read,
+                Err(err)
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        || err.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(err) => return Err(err),
+            }
+
+            let complete = filled - filled % REPORT_SIZE;
+            for report in buffer[..complete].chunks_exact(REPORT_SIZE) {
+                let tick = u32::from_le_bytes([report[4], report[5], report[6], report[7]]);
+                let levels = u32::from_le_bytes([report[8], report[9], report[10], report[11]]);
+                let level = if levels & (1 << self.gpio) != 0 {
+                    Level::High
+                } else {
+                    Level::Low
+                };
+                responded |= level == Level::Low;
+                // Reports are also sent for other GPIOs and keep-alives, only keep the changes
+                match last {
+                    Some((last_level, _)) if last_level == level => {}
+                    Some((last_level, start)) => {
+                        // Ticks wrap around every ~72 minutes
+                        parser.feed_pulse(last_level, tick.wrapping_sub(start));
+                        last = Some((level, tick));
+                        if parser.is_complete() {
+                            return Ok(());
+                        }
+                    }
+                    None => last = Some((level, tick)),
+                }
+            }
+            buffer.copy_within(complete..filled, 0);
+            filled -= complete;
+        }
+        Ok(())
+    }
+}
+
+impl Sensor<DHT11Result, DHT11Error<io::Error>> for PigpioController {
+    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error<io::Error>> {
+        self.drain_reports()?;
+
+        // Sending power pulse to indicate a start signal for the sensor
+        command(&mut self.command, PI_CMD_MODES, self.gpio, PI_OUTPUT)?;
+        command(&mut self.command, PI_CMD_WRITE, self.gpio, 1)?;
+        thread::sleep(Duration::from_millis(50));
+        command(&mut self.command, PI_CMD_WRITE, self.gpio, 0)?;
+        thread::sleep(self.model.start_signal());
+
+        // Receiving data as notifications, the tick difference between two changes is the
+        // length of a pull-up or pull-down in microseconds
+        command(&mut self.command, PI_CMD_NB, self.handle, 1 << self.gpio)?;
+        command(&mut self.command, PI_CMD_MODES, self.gpio, PI_INPUT)?;
+        command(&mut self.command, PI_CMD_PUD, self.gpio, PI_PUD_UP)?;
+        let mut parser = PulseParser::new(PulseUnit::Microseconds);
+        let collected = self.collect_edges(&mut parser);
+        command(&mut self.command, PI_CMD_NP, self.handle, 0)?;
+        collected?;
+
+        let (result, bit_decoding) = decode_reading(&parser, self.model)?;
+        self.bit_decoding = Some(bit_decoding);
+        Ok(result)
+    }
+}
+
+impl Drop for PigpioController {
+    fn drop(&mut self) {
+        let _ = command(&mut self.command, PI_CMD_NC, self.handle, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::Encoder;
+    use std::net::TcpListener;
+
+    /// GPIO of the sensor on the fake daemon.
+    const GPIO: u32 = 4;
+
+    /// Notification reports of the encoded frame, with reports of another GPIO in between.
+    fn reports(bytes: &[u8; 5]) -> Vec<u8> {
+        let mut reports = Vec::new();
+        let mut tick: u32 = 1000;
+        for (seqno, (level, duration)) in Encoder::new().pulses(bytes).into_iter().enumerate() {
+            let mut levels = ((level == Level::High) as u32) << GPIO;
+            levels |= (seqno as u32 % 2) << (GPIO + 1);
+            reports.extend_from_slice(&(seqno as u16).to_le_bytes());
+            reports.extend_from_slice(&0u16.to_le_bytes());
+            reports.extend_from_slice(&tick.to_le_bytes());
+            reports.extend_from_slice(&levels.to_le_bytes());
+            tick = tick.wrapping_add(duration.as_micros() as u32);
+        }
+        reports
+    }
+
+    /// Fake pigpio daemon answering every command with 0, which sends the reports of the frame
+    /// once the GPIO is switched to input.
+    fn fake_daemon(bytes: [u8; 5]) -> io::Result<u16> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        thread::spawn(move || -> io::Result<()> {
+            let (mut command, _) = listener.accept()?;
+            let (mut notify, _) = listener.accept()?;
+            let mut buffer = [0u8; 16];
+            notify.read_exact(&mut buffer)?;
+            notify.write_all(&buffer)?;
+
+            while command.read_exact(&mut buffer).is_ok() {
+                command.write_all(&buffer[..12])?;
+                command.write_all(&0i32.to_le_bytes())?;
+                let cmd = u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+                let mode = u32::from_le_bytes([buffer[8], buffer[9], buffer[10], buffer[11]]);
+                if cmd == PI_CMD_MODES && mode == PI_INPUT {
+                    notify.write_all(&reports(&bytes))?;
+                }
+            }
+            Ok(())
+        });
+        Ok(port)
+    }
+
+    #[test]
+    fn reads_fake_daemon() {
+        let port = fake_daemon([45, 0, 21, 3, 69]).unwrap();
+        let mut controller = PigpioController::connect(("127.0.0.1", port), GPIO).unwrap();
+
+        let result = controller.read_sensor_data().unwrap();
+        assert_eq!(result.humidity, 45.0);
+        assert_eq!(result.temperature, 21.3);
+        assert_eq!(controller.last_bit_decoding(), Some(BitDecoding::Absolute));
+    }
+
+    #[test]
+    fn rejects_gpio_outside_bank() {
+        let err = PigpioController::connect("127.0.0.1:8888", 32)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
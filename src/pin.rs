@@ -0,0 +1,107 @@
+#[cfg(feature = "rppal")]
+use core::convert::Infallible;
+#[cfg(feature = "rppal")]
+use rppal::gpio::IoPin;
+
+use core::time::Duration;
+
+/// Logic level of a GPIO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Logic low (0).
+    Low,
+    /// Logic high (1).
+    High,
+}
+
+/// Direction of a GPIO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinMode {
+    /// The pin is read from.
+    Input,
+    /// The pin is driven high or low.
+    Output,
+}
+
+/// Built-in pull-up/pull-down resistor configuration of a GPIO pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bias {
+    /// No built-in resistor.
+    Off,
+    /// Built-in pull-down resistor.
+    PullDown,
+    /// Built-in pull-up resistor.
+    PullUp,
+}
+
+/// Trait representing the GPIO pin the DHT11 sensor's data line is connected to.
+///
+/// `DHT11Controller` is generic over this trait, so any GPIO implementation (or an in-memory
+/// fake pin) can be used to talk to the sensor.
+pub trait DHT11Pin {
+    /// Error returned when an operation on the pin fails.
+    type Error;
+
+    /// Switches the pin between input and output mode.
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error>;
+    /// Configures the built-in pull-up/pull-down resistor.
+    fn set_bias(&mut self, bias: Bias) -> Result<(), Self::Error>;
+    /// Drives the pin high.
+    fn set_high(&mut self) -> Result<(), Self::Error>;
+    /// Drives the pin low.
+    fn set_low(&mut self) -> Result<(), Self::Error>;
+    /// Reads the current level of the pin.
+    fn read(&mut self) -> Result<Level, Self::Error>;
+
+    /// Returns the time of the last read, relative to an arbitrary reference point, for backends
+    /// sampling the line with their own clock. By default `None` is returned, in which case the
+    /// system clock is used with `std`, or the samples are counted without.
+    fn timestamp(&mut self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(feature = "rppal")]
+impl DHT11Pin for IoPin {
+    type Error = Infallible;
+
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error> {
+        IoPin::set_mode(
+            self,
+            match mode {
+                PinMode::Input => rppal::gpio::Mode::Input,
+                PinMode::Output => rppal::gpio::Mode::Output,
+            },
+        );
+        Ok(())
+    }
+
+    fn set_bias(&mut self, bias: Bias) -> Result<(), Self::Error> {
+        IoPin::set_bias(
+            self,
+            match bias {
+                Bias::Off => rppal::gpio::Bias::Off,
+                Bias::PullDown => rppal::gpio::Bias::PullDown,
+                Bias::PullUp => rppal::gpio::Bias::PullUp,
+            },
+        );
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        IoPin::set_high(self);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        IoPin::set_low(self);
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Level, Self::Error> {
+        Ok(match IoPin::read(self) {
+            rppal::gpio::Level::Low => Level::Low,
+            rppal::gpio::Level::High => Level::High,
+        })
+    }
+}
@@ -0,0 +1,332 @@
+use crate::pin::DHT11Pin;
+use crate::{delay_for, DHT11Controller, DHT11Error, DHT11Result, ErrorKind, Sensor};
+use core::time::Duration;
+use embedded_hal::delay::DelayNs;
+
+/// Error kinds retried by default, all those a live read can report but the backend errors, which
+/// are unlikely to go away by retrying.
+const DEFAULT_RETRY_ON: [ErrorKind; 7] = [
+    ErrorKind::NoResponse,
+    ErrorKind::PreambleTimeout,
+    ErrorKind::TooFewBits,
+    ErrorKind::InvalidChecksum,
+    ErrorKind::OutOfRange,
+    ErrorKind::InvalidData,
+    ErrorKind::TooSoon,
+];
+
+/// Policy deciding how often and how fast a `RetryingReader` retries failed reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    delay: Duration,
+    backoff: u32,
+    max_delay: Duration,
+    /// Bit set of the retried error kinds, indexed by their discriminant.
+    retry_on: u16,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy making up to 5 attempts, waiting the minimum interval of the controller
+    /// between them and retrying on all but the backend errors.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            delay: Duration::ZERO,
+            backoff: 1,
+            max_delay: Duration::from_secs(60),
+            retry_on: 0,
+        }
+        .with_retry_on(&DEFAULT_RETRY_ON)
+    }
+
+    /// Sets the maximum number of attempts, including the first one (at least 1).
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry. The delay is never shorter than the minimum
+    /// interval between two reads of the controller (by default 1s for the DHT11, 2s for the
+    /// others).
+    pub fn with_delay(mut self, delay: Duration) -> RetryPolicy {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the factor the delay is multiplied with after every retry, by default 1 (a constant
+    /// delay). A factor of 2 doubles the delay every time.
+    pub fn with_backoff(mut self, backoff: u32) -> RetryPolicy {
+        self.backoff = backoff.max(1);
+        self
+    }
+
+    /// Sets the maximum delay the backoff grows to, by default 60s.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> RetryPolicy {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the error kinds which are retried, other errors are returned immediately.
+    pub fn with_retry_on(mut self, kinds: &[ErrorKind]) -> RetryPolicy {
+        self.retry_on = kinds.iter().fold(0, |mask, &kind| mask | 1 << kind as u16);
+        self
+    }
+
+    /// Maximum number of attempts, including the first one.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Whether errors of the kind are retried.
+    pub fn retries(&self, kind: ErrorKind) -> bool {
+        self.retry_on & (1 << kind as u16) != 0
+    }
+
+    /// Delay before the specified retry (1 for the first one), at least `min_interval`.
+    pub fn delay(&self, retry: u32, min_interval: Duration) -> Duration {
+        let mut delay = self.delay.max(min_interval);
+        for _ in 1..retry {
+            if delay >= self.max_delay {
+                break;
+            }
+            delay = delay.saturating_mul(self.backoff);
+        }
+        delay.min(self.max_delay.max(min_interval))
+    }
+}
+
+/// Successful reading of a `RetryingReader`, with the number of attempts it took.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetriedReading {
+    /// Reading of the sensor.
+    pub result: DHT11Result,
+    /// Number of attempts, 1 if the first read succeeded.
+    pub attempts: u32,
+}
+
+/// Wrapper around a `DHT11Controller` retrying failed reads according to a `RetryPolicy`,
+/// waiting between the attempts with the delay provider of the controller.
+pub struct RetryingReader<P, D> {
+    controller: DHT11Controller<P, D>,
+    policy: RetryPolicy,
+}
+
+impl<P: DHT11Pin, D: DelayNs> RetryingReader<P, D> {
+    /// Creates a retrying reader with the specified policy.
+    pub fn new(controller: DHT11Controller<P, D>, policy: RetryPolicy) -> RetryingReader<P, D> {
+        RetryingReader { controller, policy }
+    }
+
+    /// Returns the retry policy.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Returns the wrapped controller, e.g. to change its model.
+    pub fn controller(&mut self) -> &mut DHT11Controller<P, D> {
+        &mut self.controller
+    }
+
+    /// Consumes the reader, returning the controller.
+    pub fn into_controller(self) -> DHT11Controller<P, D> {
+        self.controller
+    }
+
+    /// Reads the sensor, retrying failed reads. Returns the reading with the number of attempts
+    /// it took, or the error of the last attempt.
+    ///
+    /// A `DHT11Error::TooSoon` does not use up an attempt, as the sensor was not read, the reader
+    /// waits for the remaining interval and reads again. It is only returned if the first read
+    /// fails with it and `ErrorKind::TooSoon` is not retried.
+    pub fn read(&mut self) -> Result<RetriedReading, DHT11Error<P::Error>> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.controller.read_sensor_data() {
+                Ok(result) => return Ok(RetriedReading { result, attempts }),
+                // The sensor was not read, which does not count as an attempt
+                Err(DHT11Error::TooSoon(remaining))
+                    if attempts > 1 || self.policy.retries(ErrorKind::TooSoon) =>
+                {
+                    attempts -= 1;
+                    delay_for(&mut self.controller.delay, remaining);
+                }
+                Err(err)
+                    if attempts < self.policy.max_attempts && self.policy.retries(err.kind()) =>
+                {
+                    let delay = self.policy.delay(attempts, self.controller.min_interval());
+                    delay_for(&mut self.controller.delay, delay);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<P: DHT11Pin, D: DelayNs> Sensor<DHT11Result, DHT11Error<P::Error>> for RetryingReader<P, D> {
+    fn read_sensor_data(&mut self) -> Result<DHT11Result, DHT11Error<P::Error>> {
+        self.read().map(|reading| reading.result)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::capture::{Capture, ReplayPin};
+    use crate::decode::PulseUnit;
+    use crate::encode::Encoder;
+    use crate::sim::{Fault, SimulatedDelay, SimulatedPin};
+    use crate::IntervalPolicy;
+
+    /// 45% humidity and 21.3°C with a valid checksum.
+    const BYTES: [u8; 5] = [45, 0, 21, 3, 69];
+
+    /// Start signal and deadline of a read of a sensor stopping mid-transmission.
+    const FAILED_READ: Duration = Duration::from_millis(270);
+
+    fn reader(
+        pin: SimulatedPin,
+        policy: RetryPolicy,
+    ) -> RetryingReader<SimulatedPin, SimulatedDelay> {
+        let delay = pin.delay();
+        let controller = DHT11Controller::with_delay(pin, delay).with_min_interval(Duration::ZERO);
+        RetryingReader::new(controller, policy)
+    }
+
+    /// Capture of a transmission of the bytes.
+    fn capture(bytes: [u8; 5]) -> Capture {
+        let mut capture = Capture::new(PulseUnit::Microseconds);
+        capture.pulses = Encoder::new()
+            .pulses(&bytes)
+            .into_iter()
+            .map(|(level, duration)| (level, duration.as_micros() as u32))
+            .collect();
+        capture
+    }
+
+    #[test]
+    fn backs_off() {
+        let policy = RetryPolicy::new()
+            .with_delay(Duration::from_secs(1))
+            .with_backoff(2)
+            .with_max_delay(Duration::from_secs(5));
+        let delays: Vec<_> = (1..=5)
+            .map(|retry| policy.delay(retry, Duration::ZERO).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        // The minimum interval raises the first delay and the maximum
+        assert_eq!(
+            policy.delay(1, Duration::from_secs(2)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            policy.delay(2, Duration::from_secs(2)),
+            Duration::from_secs(4)
+        );
+        assert_eq!(
+            policy.delay(3, Duration::from_secs(8)),
+            Duration::from_secs(8)
+        );
+    }
+
+    #[test]
+    fn waits_between_attempts() {
+        let policy = RetryPolicy::new()
+            .with_max_attempts(3)
+            .with_delay(Duration::from_secs(2))
+            .with_backoff(2);
+        let mut reader = reader(
+            SimulatedPin::new(BYTES).with_fault(Fault::DroppedBits(20)),
+            policy,
+        );
+
+        let err = reader.read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooFewBits);
+        // Three reads with a delay of 2s and 4s between them
+        let end = Duration::from_secs(6) + FAILED_READ * 3;
+        let now = reader.into_controller().into_pin().now();
+        assert!(
+            now >= end && now < end + Duration::from_millis(1),
+            "ended at {:?}",
+            now
+        );
+    }
+
+    #[test]
+    fn returns_other_errors() {
+        let policy = RetryPolicy::new().with_retry_on(&[ErrorKind::InvalidChecksum]);
+        assert!(policy.retries(ErrorKind::InvalidChecksum));
+        assert!(!policy.retries(ErrorKind::TooFewBits));
+        let mut reader = reader(
+            SimulatedPin::new(BYTES).with_fault(Fault::DroppedBits(20)),
+            policy,
+        );
+
+        let err = reader.read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooFewBits);
+        let now = reader.into_controller().into_pin().now();
+        assert!(
+            now < FAILED_READ + Duration::from_millis(1),
+            "ended at {:?}",
+            now
+        );
+    }
+
+    #[test]
+    fn counts_attempts() {
+        let corrupted = [45, 0, 21, 3, 0];
+        let pin = ReplayPin::new([capture(corrupted), capture(corrupted), capture(BYTES)]);
+        let delay = pin.delay();
+        let controller = DHT11Controller::with_delay(pin, delay);
+        let mut reader = RetryingReader::new(controller, RetryPolicy::new());
+
+        let reading = reader.read().unwrap();
+        assert_eq!(reading.attempts, 3);
+        assert_eq!(reading.result.temperature, 21.3);
+        assert_eq!(reader.into_controller().into_pin().remaining(), 0);
+    }
+
+    #[test]
+    fn waits_when_too_soon() {
+        let pin = SimulatedPin::new(BYTES);
+        let delay = pin.delay();
+        let controller = DHT11Controller::with_delay(pin, delay)
+            .with_interval_policy(IntervalPolicy::TooSoon)
+            .with_min_interval(Duration::from_secs(5));
+        let mut reader = RetryingReader::new(controller, RetryPolicy::new().with_max_attempts(1));
+        assert_eq!(reader.read().unwrap().attempts, 1);
+
+        // Does not use up the only attempt
+        let reading = reader.read().unwrap();
+        assert_eq!(reading.attempts, 1);
+        assert!(reader.controller().dht_pin.now() >= Duration::from_secs(5));
+
+        // Unless not retried
+        reader.policy = reader.policy.with_retry_on(&[ErrorKind::TooFewBits]);
+        let err = reader.read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooSoon);
+    }
+
+    #[test]
+    fn retries_after_min_interval() {
+        let pin = SimulatedPin::new([45, 0, 21, 3, 69]).with_fault(Fault::DroppedBits(20));
+        let delay = pin.delay();
+        let controller = DHT11Controller::with_delay(pin, delay)
+            .with_interval_policy(IntervalPolicy::TooSoon)
+            .with_min_interval(Duration::from_secs(5));
+        let policy = RetryPolicy::new().with_max_attempts(3);
+        let mut reader = RetryingReader::new(controller, policy);
+
+        let err = reader.read().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooFewBits);
+        let now = reader.into_controller().into_pin().now();
+        assert!(now >= Duration::from_secs(10), "retried after {:?}", now);
+    }
+}
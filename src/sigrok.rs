@@ -0,0 +1,504 @@
+//! Import of logic analyzer captures of the DHT11 data line, exported by sigrok as CSV or saved
+//! as `.sr` session files (with the `sigrok` feature).
+//!
+//! A capture can contain any number of reads, each starting with the start signal driven by the
+//! host. The reads are split into `Capture`s and decoded with the existing decoder, reporting
+//! each frame with timing statistics.
+
+use crate::capture::Capture;
+use crate::decode::{DHT11Frame, PulseParser, PulseUnit, DATA_BITS};
+use crate::pin::Level;
+use crate::DHT11Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::time::Duration;
+
+/// Lows longer than this are a start signal driven by the host, the shortest start signal of the
+/// datasheets (DHT21 and DHT12: 800µs, see `Model::min_start_signal()`) is well above the longest
+/// low sent by the sensor (80µs).
+const MIN_START_SIGNAL: Duration = Duration::from_micros(300);
+
+/// Level of a logic analyzer channel over time, as pulses of a level and how long it was held.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Time between two samples, if known.
+    pub sample_period: Option<Duration>,
+    /// Levels and how long they were held, in the order they were sampled.
+    pub pulses: Vec<(Level, Duration)>,
+}
+
+impl Trace {
+    /// Creates a trace from the times (relative to the start of the capture) the level was
+    /// sampled at, or changed to, merging consecutive samples of the same level.
+    ///
+    /// The last level is held for one sample period if known.
+    pub fn from_samples(samples: &[(Duration, Level)], sample_period: Option<Duration>) -> Trace {
+        let mut runs = Runs::new();
+        for &(time, level) in samples {
+            runs.push(time, level);
+        }
+        runs.finish(sample_period)
+    }
+
+    /// Splits the trace at the start signals into a capture per read, with the lengths in
+    /// microseconds and the start signal in the metadata. Pulses before the first start signal
+    /// are skipped.
+    pub fn captures(&self) -> Vec<Capture> {
+        let mut captures: Vec<Capture> = Vec::new();
+        for &(level, duration) in &self.pulses {
+            if level == Level::Low && duration >= MIN_START_SIGNAL {
+                let mut capture = Capture::new(PulseUnit::Microseconds);
+                capture.metadata.backend = Some("sigrok".to_string());
+                capture.metadata.sample_period = self.sample_period;
+                capture.metadata.start_signal = Some(duration);
+                captures.push(capture);
+            } else if let Some(capture) = captures.last_mut() {
+                let length = duration.as_secs_f64() * 1_000_000.0;
+                capture.pulses.push((level, length.round() as u32));
+            }
+        }
+        captures
+    }
+
+    /// Decodes every read in the trace.
+    pub fn frames(&self) -> Vec<FrameReport> {
+        let mut reports = Vec::new();
+        let mut start = Duration::ZERO;
+        let mut captures = self.captures().into_iter();
+        let mut next = captures.next();
+        for &(level, duration) in &self.pulses {
+            if level == Level::Low && duration >= MIN_START_SIGNAL {
+                if let Some(capture) = next.take() {
+                    reports.push(FrameReport {
+                        start,
+                        frame: capture.decode(),
+                        timing: Timing::of(&capture),
+                        capture,
+                    });
+                    next = captures.next();
+                }
+            }
+            start += duration;
+        }
+        reports
+    }
+}
+
+/// Run-length encoder merging samples into the pulses of a trace, so only the level changes are
+/// stored, not every sample of a long capture.
+struct Runs {
+    pulses: Vec<(Level, Duration)>,
+    /// Time and level of the last change.
+    changed: Option<(Duration, Level)>,
+    /// Time of the last sample.
+    last: Duration,
+}
+
+impl Runs {
+    fn new() -> Runs {
+        Runs {
+            pulses: Vec::new(),
+            changed: None,
+            last: Duration::ZERO,
+        }
+    }
+
+    /// Adds the level sampled at a time, relative to the start of the capture.
+    fn push(&mut self, time: Duration, level: Level) {
+        match self.changed {
+            Some((_, last)) if last == level => {}
+            Some((since, last)) => {
+                self.pulses.push((last, time.saturating_sub(since)));
+                self.changed = Some((time, level));
+            }
+            None => self.changed = Some((time, level)),
+        }
+        self.last = time;
+    }
+
+    /// Completes the trace, the last level is held for one sample period if known.
+    fn finish(mut self, sample_period: Option<Duration>) -> Trace {
+        if let Some((since, level)) = self.changed {
+            let length = self.last.saturating_sub(since) + sample_period.unwrap_or_default();
+            self.pulses.push((level, length));
+        }
+        Trace {
+            sample_period,
+            pulses: self.pulses,
+        }
+    }
+}
+
+/// Sample rate of a capture in hertz.
+#[derive(Debug, Clone, Copy)]
+struct SampleRate(f64);
+
+impl SampleRate {
+    /// Time between two samples, rounded to nanoseconds.
+    fn period(self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.0)
+    }
+
+    /// Time of the sample with the index, calculated from the rate so the rounding of the period
+    /// does not add up over a long capture.
+    fn time(self, index: u64) -> Duration {
+        Duration::from_nanos((index as f64 * 1e9 / self.0).round() as u64)
+    }
+}
+
+/// Minimum, maximum and mean of a set of pulse lengths in microseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    /// Number of pulses.
+    pub count: usize,
+    /// Shortest length.
+    pub min: u32,
+    /// Longest length.
+    pub max: u32,
+    /// Mean length.
+    pub mean: f64,
+}
+
+impl Stats {
+    /// Calculates the statistics of the lengths, `None` if there are none.
+    pub fn of<I: IntoIterator<Item = u32>>(lengths: I) -> Option<Stats> {
+        let mut stats: Option<Stats> = None;
+        for length in lengths {
+            let stats = stats.get_or_insert(Stats {
+                count: 0,
+                min: u32::MAX,
+                max: 0,
+                mean: 0.0,
+            });
+            stats.count += 1;
+            stats.min = stats.min.min(length);
+            stats.max = stats.max.max(length);
+            stats.mean += (length as f64 - stats.mean) / stats.count as f64;
+        }
+        stats
+    }
+}
+
+/// Timing of the pulses of a read, the lengths are in microseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    /// Length of the start signal driven by the host.
+    pub start_signal: Option<Duration>,
+    /// Low of the sensor response (datasheet: 80µs).
+    pub response_low: Option<u32>,
+    /// High of the sensor response (datasheet: 80µs).
+    pub response_high: Option<u32>,
+    /// Lows preceding each bit (datasheet: 50µs).
+    pub bit_low: Option<Stats>,
+    /// Pull-ups of the `0` bits (datasheet: 26-28µs), only known if the frame was decoded.
+    pub zero_high: Option<Stats>,
+    /// Pull-ups of the `1` bits (datasheet: 70µs), only known if the frame was decoded.
+    pub one_high: Option<Stats>,
+}
+
+impl Timing {
+    /// Measures the timing of the pulses of a capture, which has to be in microseconds.
+    pub fn of(capture: &Capture) -> Timing {
+        let bytes = capture.decode().ok().map(|frame| frame.bytes);
+
+        // Indices of the low and high pulse of each bit
+        let mut bits: Vec<(usize, usize)> = Vec::with_capacity(DATA_BITS);
+        let mut parser = PulseParser::new(capture.unit);
+        for (index, &(level, length)) in capture.pulses.iter().enumerate() {
+            let count = parser.bit_count();
+            parser.feed_pulse(level, length);
+            if parser.bit_count() > count && count < DATA_BITS && index >= 2 {
+                bits.push((index - 2, index - 1));
+            }
+        }
+
+        let length = |index: usize| capture.pulses[index].1;
+        let response = bits.first().map(|&(low, _)| low).filter(|&low| low >= 2);
+        let bit_value = |bit: usize| bytes.map(|bytes| bytes[bit / 8] & (0x80 >> (bit % 8)) != 0);
+        let highs = |value: bool| {
+            Stats::of(
+                bits.iter()
+                    .enumerate()
+                    .filter(|&(bit, _)| bit_value(bit) == Some(value))
+                    .map(|(_, &(_, high))| length(high)),
+            )
+        };
+        Timing {
+            start_signal: capture.metadata.start_signal,
+            response_low: response.map(|low| length(low - 2)),
+            response_high: response.map(|low| length(low - 1)),
+            bit_low: Stats::of(bits.iter().map(|&(low, _)| length(low))),
+            zero_high: highs(false),
+            one_high: highs(true),
+        }
+    }
+}
+
+/// Frame decoded from a read in a logic analyzer capture.
+#[derive(Debug)]
+pub struct FrameReport {
+    /// Time of the start signal, relative to the start of the capture.
+    pub start: Duration,
+    /// Pulses of the read.
+    pub capture: Capture,
+    /// Decoded frame, or the reason decoding failed. The checksum is not validated.
+    pub frame: Result<DHT11Frame, DHT11Error>,
+    /// Timing of the pulses.
+    pub timing: Timing,
+}
+
+/// Reads a CSV export of sigrok (`sigrok-cli -O csv`), or a similar CSV of another logic
+/// analyzer, selecting the channel with the specified name, or the first one.
+///
+/// Lines starting with `;` are comments, the sample rate is taken from a `; Samplerate: 1 MHz`
+/// comment. An optional header row names the columns, a column named `Time...` holds the time of
+/// each sample (or level change) in seconds, otherwise the sample rate is required.
+pub fn read_csv<R: BufRead>(reader: R, channel: Option<&str>) -> io::Result<Trace> {
+    let mut sample_rate: Option<SampleRate> = None;
+    let mut time_column: Option<usize> = None;
+    let mut level_column: Option<usize> = None;
+    let mut runs = Runs::new();
+    let mut index: u64 = 0;
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix(';') {
+            if let Some((key, value)) = comment.split_once(':') {
+                if key.trim().eq_ignore_ascii_case("samplerate") {
+                    sample_rate = Some(parse_sample_rate(value.trim())?);
+                }
+            }
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if level_column.is_none() {
+            let header = fields.iter().any(|field| field.parse::<f64>().is_err());
+            if header {
+                time_column = fields
+                    .iter()
+                    .position(|field| field.to_ascii_lowercase().starts_with("time"));
+            }
+            let column = match channel {
+                Some(channel) if header => fields.iter().position(|field| *field == channel),
+                Some(channel) => channel.parse().ok().filter(|&column| column < fields.len()),
+                None => (0..fields.len()).find(|&column| Some(column) != time_column),
+            };
+            level_column = Some(column.ok_or_else(|| {
+                invalid_data(format!("channel not found: {}", channel.unwrap_or("")))
+            })?);
+            if header {
+                continue;
+            }
+        }
+
+        let field = |column: usize| {
+            fields
+                .get(column)
+                .copied()
+                .ok_or_else(|| invalid_data(format!("missing column: {}", line)))
+        };
+        let level = match field(level_column.unwrap_or_default())? {
+            "0" => Level::Low,
+            "1" => Level::High,
+            level => return Err(invalid_data(format!("invalid level: {}", level))),
+        };
+        let time = match (time_column, sample_rate) {
+            (Some(column), _) => {
+                let seconds: f64 = field(column)?
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid time: {}", line)))?;
+                Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| invalid_data(format!("invalid time: {}", line)))?
+            }
+            (None, Some(sample_rate)) => sample_rate.time(index),
+            (None, None) => return Err(invalid_data("missing sample rate".to_string())),
+        };
+        runs.push(time, level);
+        index += 1;
+    }
+    Ok(runs.finish(sample_rate.map(SampleRate::period)))
+}
+
+/// Loads a CSV export of a logic analyzer, see `read_csv()`.
+pub fn load_csv<Q: AsRef<Path>>(path: Q, channel: Option<&str>) -> io::Result<Trace> {
+    read_csv(BufReader::new(File::open(path)?), channel)
+}
+
+/// Loads a sigrok session file (`.sr`), selecting the logic channel with the specified name, or
+/// the first one.
+#[cfg(feature = "sigrok")]
+pub fn load_sr<Q: AsRef<Path>>(path: Q, channel: Option<&str>) -> io::Result<Trace> {
+    use std::io::Read;
+
+    let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+    let mut metadata = String::new();
+    archive
+        .by_name("metadata")
+        .map_err(zip_error)?
+        .read_to_string(&mut metadata)?;
+
+    // The first device section describes the logic channels
+    let mut capture_file: Option<String> = None;
+    let mut sample_rate: Option<SampleRate> = None;
+    let mut unit_size: usize = 1;
+    let mut bit: Option<usize> = None;
+    for line in metadata.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match key {
+            "capturefile" => capture_file = Some(value.to_string()),
+            "samplerate" => sample_rate = Some(parse_sample_rate(value)?),
+            "unitsize" => unit_size = value.parse().map_err(|_| invalid_data(line.to_string()))?,
+            _ => {
+                // Logic channels are named `probe1`, `probe2`, ... with the name as the value
+                let Some(probe) = key.strip_prefix("probe") else {
+                    continue;
+                };
+                let Ok(probe) = probe.parse::<usize>() else {
+                    continue;
+                };
+                let selected = match channel {
+                    Some(channel) => value == channel,
+                    None => bit.is_none(),
+                };
+                if selected && probe >= 1 {
+                    bit = Some(probe - 1);
+                }
+            }
+        }
+    }
+    let capture_file = capture_file.ok_or_else(|| invalid_data("missing capturefile".into()))?;
+    let sample_rate = sample_rate.ok_or_else(|| invalid_data("missing samplerate".into()))?;
+    let bit =
+        bit.ok_or_else(|| invalid_data(format!("channel not found: {}", channel.unwrap_or(""))))?;
+
+    // The samples are stored in chunks named `<capturefile>-1`, `<capturefile>-2`, ..., or in a
+    // single file in older versions. The chunks are decoded one at a time, keeping the bytes of
+    // a sample split across two chunks.
+    let chunks: Vec<String> = if archive.index_for_name(&capture_file).is_some() {
+        vec![capture_file]
+    } else {
+        (1..)
+            .map(|chunk| format!("{}-{}", capture_file, chunk))
+            .take_while(|name| archive.index_for_name(name).is_some())
+            .collect()
+    };
+    let mut runs = Runs::new();
+    let mut index: u64 = 0;
+    let mut data: Vec<u8> = Vec::new();
+    for name in chunks {
+        archive
+            .by_name(&name)
+            .map_err(zip_error)?
+            .read_to_end(&mut data)?;
+        let complete = data.len() - data.len() % unit_size;
+        for sample in data[..complete].chunks_exact(unit_size) {
+            let level = match sample.get(bit / 8).map(|byte| byte & (1 << (bit % 8))) {
+                Some(0) | None => Level::Low,
+                Some(_) => Level::High,
+            };
+            runs.push(sample_rate.time(index), level);
+            index += 1;
+        }
+        data.drain(..complete);
+    }
+    Ok(runs.finish(Some(sample_rate.period())))
+}
+
+/// Parses a sample rate like `1 MHz`.
+fn parse_sample_rate(rate: &str) -> io::Result<SampleRate> {
+    let rate = rate.trim();
+    let split = rate
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(rate.len());
+    let (value, unit) = rate.split_at(split);
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "hz" => 1.0,
+        "khz" => 1e3,
+        "mhz" => 1e6,
+        "ghz" => 1e9,
+        _ => return Err(invalid_data(format!("invalid sample rate: {}", rate))),
+    };
+    let hertz = value.parse::<f64>().unwrap_or(0.0) * multiplier;
+    if hertz <= 0.0 {
+        return Err(invalid_data(format!("invalid sample rate: {}", rate)));
+    }
+    Ok(SampleRate(hertz))
+}
+
+/// Converts an error reading a session file.
+#[cfg(feature = "sigrok")]
+fn zip_error(err: zip::result::ZipError) -> io::Error {
+    invalid_data(err.to_string())
+}
+
+/// Creates an `InvalidData` error for a malformed capture.
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CSV export of a channel held at each level for the number of samples.
+    fn csv(rate: &str, runs: &[(&str, usize)]) -> String {
+        let mut csv = format!("; Samplerate: {}\nD0\n", rate);
+        for &(level, samples) in runs {
+            for _ in 0..samples {
+                csv.push_str(level);
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+
+    #[test]
+    fn merges_samples() {
+        let csv = csv("1 MHz", &[("1", 30), ("0", 80), ("1", 80), ("0", 50)]);
+        let trace = read_csv(csv.as_bytes(), Some("D0")).unwrap();
+        assert_eq!(trace.sample_period, Some(Duration::from_micros(1)));
+        assert_eq!(
+            trace.pulses,
+            [
+                (Level::High, Duration::from_micros(30)),
+                (Level::Low, Duration::from_micros(80)),
+                (Level::High, Duration::from_micros(80)),
+                (Level::Low, Duration::from_micros(50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn times_samples_without_drift() {
+        // The period of 41.67ns is rounded, which would add up to a drift of 1.6%
+        let csv = csv("24 MHz", &[("1", 240_000), ("0", 240_000), ("1", 1)]);
+        let trace = read_csv(csv.as_bytes(), None).unwrap();
+        assert_eq!(trace.pulses.len(), 3);
+        assert_eq!(trace.pulses[0], (Level::High, Duration::from_millis(10)));
+        assert_eq!(trace.pulses[1], (Level::Low, Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn detects_start_signal_of_every_model() {
+        use crate::Model;
+
+        for model in [
+            Model::DHT11,
+            Model::DHT11Legacy,
+            Model::DHT22,
+            Model::DHT21,
+            Model::DHT12,
+        ] {
+            assert!(model.min_start_signal() > MIN_START_SIGNAL, "{:?}", model);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Simulated DHT11 sensor for testing `DHT11Controller` without hardware.
+//!
+//! The simulated pin runs on a virtual clock, which is advanced by every read of the pin and by
+//! the paired `SimulatedDelay`, so the readings are fully deterministic.
+
+use crate::decode::DATA_BYTES;
+use crate::encode::Encoder;
+use crate::model::Model;
+use crate::pin::{Bias, DHT11Pin, Level, PinMode};
+use core::convert::Infallible;
+use embedded_hal::delay::DelayNs;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Fault injected into the simulated transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The sensor never responds to the start signal.
+    NoResponse,
+    /// The sensor stops transmitting after the specified number of bits.
+    DroppedBits(usize),
+    /// The checksum byte does not match the data bytes.
+    CorruptedChecksum,
+    /// The line is stuck at the level after the start signal.
+    StuckLine(Level),
+}
+
+/// Simulated pin with a DHT11 sensor connected, implementing `DHT11Pin`.
+pub struct SimulatedPin {
+    /// Virtual clock shared with the delays created by `delay()`.
+    clock: Rc<Cell<Duration>>,
+    /// Time advanced by every read of the pin.
+    sample_period: Duration,
+    /// Frame sent in response to the start signal.
+    bytes: [u8; DATA_BYTES],
+    /// Encoder generating the signal of the frame.
+    encoder: Encoder,
+    /// Fault injected into the transmission.
+    fault: Option<Fault>,
+    /// Simulated sensor model, determining the minimum start signal.
+    model: Model,
+    mode: PinMode,
+    /// Level driven in output mode.
+    output: Level,
+    /// Time the line was driven low, if it still is.
+    low_since: Option<Duration>,
+    /// Pulses of the response currently being sent, with the time it started.
+    response: Option<(Duration, Vec<(Level, Duration)>)>,
+    /// Level of the line while no response is sent.
+    idle: Level,
+}
+
+/// Delay advancing the virtual clock of a `SimulatedPin` instead of sleeping.
+#[derive(Clone)]
+pub struct SimulatedDelay {
+    clock: Rc<Cell<Duration>>,
+}
+
+impl SimulatedDelay {
+    /// Creates a delay advancing the virtual clock.
+    pub(crate) fn new(clock: Rc<Cell<Duration>>) -> SimulatedDelay {
+        SimulatedDelay { clock }
+    }
+}
+
+impl DelayNs for SimulatedDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.clock
+            .set(self.clock.get() + Duration::from_nanos(ns as u64));
+    }
+}
+
+impl SimulatedPin {
+    /// Creates a simulated sensor responding with the 5 frame bytes (see
+    /// `encode::with_checksum()`), sampled every microsecond.
+    pub fn new(bytes: [u8; DATA_BYTES]) -> SimulatedPin {
+        SimulatedPin {
+            clock: Rc::new(Cell::new(Duration::ZERO)),
+            sample_period: Duration::from_micros(1),
+            bytes,
+            encoder: Encoder::new(),
+            fault: None,
+            model: Model::DHT11,
+            mode: PinMode::Input,
+            output: Level::High,
+            low_since: None,
+            response: None,
+            idle: Level::High,
+        }
+    }
+
+    /// Creates a delay advancing the virtual clock of this pin, to be passed to
+    /// `DHT11Controller::with_delay()`.
+    pub fn delay(&self) -> SimulatedDelay {
+        SimulatedDelay::new(self.clock.clone())
+    }
+
+    /// Sets the time advanced by every read of the pin.
+    pub fn with_sample_period(mut self, sample_period: Duration) -> SimulatedPin {
+        self.sample_period = sample_period;
+        self
+    }
+
+    /// Sets the encoder generating the signal, e.g. to add jitter.
+    pub fn with_encoder(mut self, encoder: Encoder) -> SimulatedPin {
+        self.encoder = encoder;
+        self
+    }
+
+    /// Sets the simulated sensor model, by default a DHT11. The frame bytes have to be encoded
+    /// for the model.
+    pub fn with_model(mut self, model: Model) -> SimulatedPin {
+        self.model = model;
+        self
+    }
+
+    /// Injects a fault into the following transmissions.
+    pub fn with_fault(mut self, fault: Fault) -> SimulatedPin {
+        self.fault = Some(fault);
+        self
+    }
+
+    /// Sets the frame bytes sent by the following transmissions.
+    pub fn set_bytes(&mut self, bytes: [u8; DATA_BYTES]) {
+        self.bytes = bytes;
+    }
+
+    /// Sets or clears the fault injected into the following transmissions.
+    pub fn set_fault(&mut self, fault: Option<Fault>) {
+        self.fault = fault;
+    }
+
+    /// Current time of the virtual clock.
+    pub fn now(&self) -> Duration {
+        self.clock.get()
+    }
+
+    /// Duration of the response to a start signal, from the release of the line to the end of
+    /// the trailing low of the last bit.
+    #[cfg(test)]
+    pub(crate) fn response_duration(&self) -> Duration {
+        let pulses = self.encoder.clone().pulses(&self.bytes);
+        pulses[..pulses.len() - 1]
+            .iter()
+            .map(|&(_, duration)| duration)
+            .sum()
+    }
+
+    /// Starts the response if the line was pulled low long enough for a start signal.
+    fn release(&mut self) {
+        let now = self.now();
+        let start_signal = match self.low_since.take() {
+            Some(low_since) => now - low_since >= self.model.min_start_signal(),
+            None => false,
+        };
+        if !start_signal {
+            return;
+        }
+
+        let mut bytes = self.bytes;
+        if self.fault == Some(Fault::CorruptedChecksum) {
+            bytes[4] = !bytes[4];
+        }
+        let mut pulses = self.encoder.pulses(&bytes);
+
+        match self.fault {
+            Some(Fault::NoResponse) => return,
+            Some(Fault::StuckLine(level)) => {
+                self.idle = level;
+                return;
+            }
+            Some(Fault::DroppedBits(bits)) => {
+                // Release, response low and high, a low and a high per bit and the low
+                // completing the last bit
+                pulses.truncate(3 + 2 * bits.min(40) + 1);
+                pulses.push((Level::High, self.encoder.timing.idle));
+            }
+            _ => {}
+        }
+        self.idle = Level::High;
+        self.response = Some((now, pulses));
+    }
+
+    /// Level of the line at the current time while not driven.
+    fn line_level(&mut self) -> Level {
+        if let Some((start, pulses)) = &self.response {
+            let mut end = *start;
+            for &(level, duration) in pulses {
+                end += duration;
+                if self.now() < end {
+                    return level;
+                }
+            }
+            self.response = None;
+        }
+        self.idle
+    }
+}
+
+impl DHT11Pin for SimulatedPin {
+    type Error = Infallible;
+
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error> {
+        if self.mode == PinMode::Output && mode == PinMode::Input {
+            self.release();
+        }
+        if mode == PinMode::Output {
+            self.response = None;
+            if self.output == Level::Low {
+                self.low_since = Some(self.now());
+            }
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    fn set_bias(&mut self, _bias: Bias) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.output = Level::High;
+        self.low_since = None;
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        if self.output != Level::Low || self.low_since.is_none() {
+            self.low_since = Some(self.now());
+        }
+        self.output = Level::Low;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Level, Self::Error> {
+        self.clock.set(self.clock.get() + self.sample_period);
+        Ok(match self.mode {
+            PinMode::Output => self.output,
+            PinMode::Input => self.line_level(),
+        })
+    }
+
+    fn timestamp(&mut self) -> Option<Duration> {
+        Some(self.now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{PulseUnit, DATA_BITS};
+    use crate::{DHT11Controller, DHT11Error, ErrorKind, Reading};
+
+    /// 45% humidity and 21.3°C with a valid checksum.
+    const BYTES: [u8; DATA_BYTES] = [45, 0, 21, 3, 69];
+
+    fn read(fault: Option<Fault>) -> Result<Reading, DHT11Error<Infallible>> {
+        let mut pin = SimulatedPin::new(BYTES);
+        pin.set_fault(fault);
+        let delay = pin.delay();
+        DHT11Controller::with_delay(pin, delay).read()
+    }
+
+    /// Asserts that the pull-up lengths classify into the leading bits of the frame.
+    fn assert_bits(lengths: &[u16]) {
+        for (i, &length) in lengths.iter().enumerate() {
+            let bit = BYTES[i / 8] & (0x80 >> (i % 8)) != 0;
+            assert_eq!(length > 50, bit, "bit {} with pull-up {}µs", i, length);
+        }
+    }
+
+    #[test]
+    fn reads_frame() {
+        let reading = read(None).unwrap();
+        assert_eq!(reading.result.humidity, 45.0);
+        assert_eq!(reading.result.temperature, 21.3);
+        assert_eq!(reading.age, Duration::ZERO);
+    }
+
+    #[test]
+    fn no_response() {
+        let err = read(Some(Fault::NoResponse)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NoResponse);
+        assert_eq!(err.evidence(), None);
+    }
+
+    #[test]
+    fn dropped_bits() {
+        let err = read(Some(Fault::DroppedBits(20))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TooFewBits);
+        let evidence = err.evidence().unwrap();
+        assert_eq!(evidence.bit_count, 20);
+        assert_eq!(evidence.unit, PulseUnit::Microseconds);
+        assert_eq!(evidence.bytes, None);
+        assert_eq!(evidence.pull_up_lengths().len(), 20);
+        assert_bits(evidence.pull_up_lengths());
+    }
+
+    #[test]
+    fn corrupted_checksum() {
+        match read(Some(Fault::CorruptedChecksum)).unwrap_err() {
+            DHT11Error::InvalidChecksum {
+                expected,
+                got,
+                evidence,
+            } => {
+                assert_eq!(expected, 69);
+                assert_eq!(got, !69);
+                assert_eq!(evidence.bit_count, DATA_BITS);
+                assert_eq!(evidence.bytes, Some([45, 0, 21, 3, !69]));
+                assert_bits(&evidence.pull_up_lengths()[..32]);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn stuck_line_low() {
+        let err = read(Some(Fault::StuckLine(Level::Low))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PreambleTimeout);
+        let evidence = err.evidence().unwrap();
+        assert_eq!(evidence.bit_count, 0);
+        assert_eq!(evidence.bytes, None);
+    }
+
+    #[test]
+    fn stuck_line_high() {
+        let err = read(Some(Fault::StuckLine(Level::High))).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NoResponse);
+        assert_eq!(err.evidence(), None);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::pin::{Bias, DHT11Pin, Level, PinMode};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Default sysfs directory of the legacy GPIO interface.
+const SYSFS_ROOT: &str = "/sys/class/gpio";
+
+/// How many times to retry opening the pin files after exporting, while udev fixes permissions.
+const EXPORT_RETRIES: u32 = 20;
+
+/// Pin backend using the deprecated sysfs GPIO interface (`/sys/class/gpio`).
+///
+/// The pin is exported when it is created (unless it already was) and unexported again when it is
+/// dropped. sysfs does not allow configuring the bias, so an external pull-up resistor is required.
+pub struct SysfsPin {
+    /// sysfs root directory containing `export` and `unexport`.
+    root: PathBuf,
+    /// GPIO number of the pin.
+    pin: u32,
+    /// Whether the pin was exported by us and has to be unexported on drop.
+    exported: bool,
+    /// Open `direction` file of the pin.
+    direction: File,
+    /// Open `value` file of the pin.
+    value: File,
+}
+
+impl SysfsPin {
+    /// Exports the specified GPIO through `/sys/class/gpio`.
+    pub fn new(pin: u32) -> io::Result<SysfsPin> {
+        SysfsPin::with_root(SYSFS_ROOT, pin)
+    }
+
+    /// Exports the specified GPIO through a custom sysfs root directory.
+    pub fn with_root<R: AsRef<Path>>(root: R, pin: u32) -> io::Result<SysfsPin> {
+        SysfsPin::with_root_and_wait(root, pin, || thread::sleep(Duration::from_millis(10)))
+    }
+
+    /// Exports the specified GPIO through a custom sysfs root directory, calling `wait` between
+    /// the attempts to open the pin files while the kernel creates them (at most 20 times before
+    /// giving up), e.g. to create the files in a test instead of sleeping.
+    pub fn with_root_and_wait<R: AsRef<Path>, W: FnMut()>(
+        root: R,
+        pin: u32,
+        mut wait: W,
+    ) -> io::Result<SysfsPin> {
+        let root = root.as_ref().to_path_buf();
+        let pin_dir = root.join(format!("gpio{}", pin));
+
+        let exported = !pin_dir.exists();
+        if exported {
+            fs::write(root.join("export"), pin.to_string())?;
+        }
+
+        // The pin files are created asynchronously and their permissions may be fixed up by udev
+        let mut retries = 0;
+        let (direction, value) = loop {
+            let files = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(pin_dir.join("direction"))
+                .and_then(|direction| {
+                    let value = OpenOptions::new()
+                        .read(true)
+                        .write(true)
+                        .open(pin_dir.join("value"))?;
+                    Ok((direction, value))
+                });
+            match files {
+                Ok(files) => break files,
+                Err(_) if retries < EXPORT_RETRIES => {
+                    retries += 1;
+                    wait();
+                }
+                Err(err) => {
+                    if exported {
+                        let _ = fs::write(root.join("unexport"), pin.to_string());
+                    }
+                    return Err(err);
+                }
+            }
+        };
+
+        Ok(SysfsPin {
+            root,
+            pin,
+            exported,
+            direction,
+            value,
+        })
+    }
+}
+
+impl DHT11Pin for SysfsPin {
+    type Error = io::Error;
+
+    fn set_mode(&mut self, mode: PinMode) -> Result<(), Self::Error> {
+        let direction: &[u8] = match mode {
+            PinMode::Input => b"in",
+            // Switching to output at the idle high level, "out" would drive the line low
+            PinMode::Output => b"high",
+        };
+        self.direction.write_at(direction, 0)?;
+        Ok(())
+    }
+
+    fn set_bias(&mut self, _bias: Bias) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.value.write_at(b"1", 0)?;
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.value.write_at(b"0", 0)?;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Level, Self::Error> {
+        let mut value = [0u8; 1];
+        self.value.read_at(&mut value, 0)?;
+        Ok(if value[0] == b'1' {
+            Level::High
+        } else {
+            Level::Low
+        })
+    }
+}
+
+impl Drop for SysfsPin {
+    fn drop(&mut self) {
+        if self.exported {
+            let _ = fs::write(self.root.join("unexport"), self.pin.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// Temporary sysfs root with empty `export` and `unexport` files.
+    fn root(name: &str) -> TempDir {
+        let root = TempDir::new(name);
+        root.write("export", "");
+        root.write("unexport", "");
+        root
+    }
+
+    /// Creates the pin files like the kernel does when the pin is exported.
+    fn create_pin(root: &TempDir, pin: u32) {
+        root.write(&format!("gpio{}/direction", pin), "in");
+        root.write(&format!("gpio{}/value", pin), "0");
+    }
+
+    #[test]
+    fn exports_and_unexports() {
+        let root = root("export");
+        let mut waits = 0;
+        let mut pin = SysfsPin::with_root_and_wait(root.path(), 4, || {
+            waits += 1;
+            create_pin(&root, 4);
+        })
+        .unwrap();
+        assert_eq!(waits, 1);
+        assert_eq!(root.read("export"), "4");
+
+        pin.set_mode(PinMode::Output).unwrap();
+        assert_eq!(root.read("gpio4/direction"), "high");
+        pin.set_low().unwrap();
+        assert_eq!(root.read("gpio4/value"), "0");
+        pin.set_high().unwrap();
+        assert_eq!(root.read("gpio4/value"), "1");
+        assert_eq!(pin.read().unwrap(), Level::High);
+        pin.set_mode(PinMode::Input).unwrap();
+        // sysfs replaces the whole value, the regular file is only overwritten at the start
+        assert!(root.read("gpio4/direction").starts_with("in"));
+
+        assert_eq!(root.read("unexport"), "");
+        drop(pin);
+        assert_eq!(root.read("unexport"), "4");
+    }
+
+    #[test]
+    fn keeps_exported_pin() {
+        let root = root("exported");
+        create_pin(&root, 17);
+        let pin =
+            SysfsPin::with_root_and_wait(root.path(), 17, || panic!("already exported")).unwrap();
+        drop(pin);
+        assert_eq!(root.read("export"), "");
+        assert_eq!(root.read("unexport"), "");
+    }
+
+    #[test]
+    fn unexports_on_failure() {
+        let root = root("failure");
+        let mut waits = 0;
+        let err = SysfsPin::with_root_and_wait(root.path(), 4, || waits += 1).err();
+        assert_eq!(err.map(|err| err.kind()), Some(io::ErrorKind::NotFound));
+        assert_eq!(waits, EXPORT_RETRIES);
+        assert_eq!(root.read("unexport"), "4");
+    }
+}
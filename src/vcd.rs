@@ -0,0 +1,234 @@
+//! Export of captures as Value Change Dumps, to inspect the timing of a read in a waveform viewer
+//! like GTKWave, annotated with the bits and bytes decoded from it.
+
+use crate::capture::Capture;
+use crate::decode::{PulseParser, DATA_BITS};
+use crate::pin::Level;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Identifier of the line level in the dump.
+const DATA: char = 'd';
+/// Identifier of the signal set while the line is driven by the host.
+const HOST: char = 'h';
+/// Identifier of the index of the bit being received.
+const BIT_INDEX: char = 'i';
+/// Identifier of the value of the last received bit.
+const BIT: char = 'b';
+/// Identifier of the value of the last received byte.
+const BYTE: char = 'y';
+
+/// Value of a signal at a point in time.
+enum Value {
+    Bit(Option<bool>),
+    Vector(Option<u8>, usize),
+}
+
+impl Capture {
+    /// Writes the capture as a Value Change Dump, e.g. to inspect it in GTKWave next to a scope
+    /// capture. The times are in nanoseconds from the start signal (if known), the dump contains:
+    ///
+    /// - `data`: level of the line.
+    /// - `host`: set while the host drives the start signal.
+    /// - `bit_index`: index of the bit being received, changing at the bit boundaries.
+    /// - `bit`: value of the last received bit, set when its pull-up ends.
+    /// - `byte`: value of the last received byte, set when its last bit ends.
+    ///
+    /// The bit and byte values are only known if the capture decodes into a frame, the checksum
+    /// is not validated.
+    pub fn write_vcd<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let bytes = self.decode().ok().map(|frame| frame.bytes);
+        let bit = |index: usize| bytes.map(|bytes| bytes[index / 8] & (0x80 >> (index % 8)) != 0);
+
+        let mut changes: Vec<(Duration, char, Value)> = Vec::new();
+        let mut time = Duration::ZERO;
+        if let Some(start_signal) = self.metadata.start_signal {
+            changes.push((time, HOST, Value::Bit(Some(true))));
+            changes.push((time, DATA, Value::Bit(Some(false))));
+            time += start_signal;
+        }
+        changes.push((time, HOST, Value::Bit(Some(false))));
+
+        // Start times of the pulses, a bit starts with the low preceding its pull-up
+        let mut starts: Vec<Duration> = Vec::with_capacity(self.pulses.len());
+        let mut parser = PulseParser::new(self.unit);
+        for &(level, length) in &self.pulses {
+            let count = parser.bit_count();
+            parser.feed_pulse(level, length);
+            if parser.bit_count() > count && count < DATA_BITS && starts.len() >= 2 {
+                // The bit started with the low preceding its pull-up
+                changes.push((starts[starts.len() - 2], BIT_INDEX, index(count)));
+                changes.push((time, BIT, Value::Bit(bit(count))));
+                if count % 8 == 7 {
+                    let byte = bytes.map(|bytes| bytes[count / 8]);
+                    changes.push((time, BYTE, Value::Vector(byte, 8)));
+                }
+                if count == DATA_BITS - 1 {
+                    changes.push((time, BIT_INDEX, Value::Vector(None, 6)));
+                }
+            }
+            starts.push(time);
+            changes.push((time, DATA, Value::Bit(Some(level == Level::High))));
+            time += self.duration(length);
+        }
+        // Stable sort, keeping the order of the changes at the same time
+        changes.sort_by_key(|&(time, _, _)| time);
+
+        if let Some(timestamp) = self.metadata.timestamp {
+            let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            writeln!(
+                writer,
+                "$date {}.{:06} seconds since the Unix epoch $end",
+                timestamp.as_secs(),
+                timestamp.subsec_micros()
+            )?;
+        }
+        writeln!(writer, "$version dht11_gpio $end")?;
+        writeln!(writer, "$timescale 1ns $end")?;
+        writeln!(writer, "$scope module dht11 $end")?;
+        writeln!(writer, "$var wire 1 {} data $end", DATA)?;
+        writeln!(writer, "$var wire 1 {} host $end", HOST)?;
+        writeln!(writer, "$var reg 6 {} bit_index $end", BIT_INDEX)?;
+        writeln!(writer, "$var wire 1 {} bit $end", BIT)?;
+        writeln!(writer, "$var reg 8 {} byte $end", BYTE)?;
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+        writeln!(writer, "$dumpvars")?;
+        writeln!(writer, "x{}", DATA)?;
+        writeln!(writer, "x{}", HOST)?;
+        writeln!(writer, "bx {}", BIT_INDEX)?;
+        writeln!(writer, "x{}", BIT)?;
+        writeln!(writer, "bx {}", BYTE)?;
+        writeln!(writer, "$end")?;
+
+        let mut last_time = None;
+        for (time, id, value) in changes {
+            if last_time != Some(time) {
+                writeln!(writer, "#{}", time.as_nanos())?;
+                last_time = Some(time);
+            }
+            match value {
+                Value::Bit(Some(value)) => writeln!(writer, "{}{}", value as u8, id)?,
+                Value::Bit(None) => writeln!(writer, "x{}", id)?,
+                Value::Vector(Some(value), width) => {
+                    writeln!(writer, "b{:0width$b} {}", value, id, width = width)?
+                }
+                Value::Vector(None, _) => writeln!(writer, "bx {}", id)?,
+            }
+        }
+        writeln!(writer, "#{}", time.as_nanos())?;
+        writer.flush()
+    }
+
+    /// Writes the capture as a Value Change Dump file, see `write_vcd()`.
+    pub fn save_vcd<Q: AsRef<Path>>(&self, path: Q) -> io::Result<()> {
+        self.write_vcd(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Value of the bit index signal.
+fn index(index: usize) -> Value {
+    Value::Vector(Some(index as u8), 6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::PulseUnit;
+    use crate::encode::Encoder;
+
+    /// Dump of the first two bits of a transmission, which do not decode into a frame.
+    const GOLDEN: &str = "\
+$date 1.250000 seconds since the Unix epoch $end
+$version dht11_gpio $end
+$timescale 1ns $end
+$scope module dht11 $end
+$var wire 1 d data $end
+$var wire 1 h host $end
+$var reg 6 i bit_index $end
+$var wire 1 b bit $end
+$var reg 8 y byte $end
+$upscope $end
+$enddefinitions $end
+$dumpvars
+xd
+xh
+bx i
+xb
+bx y
+$end
+#0
+1h
+0d
+#20000000
+0h
+1d
+#20030000
+0d
+#20110000
+1d
+#20190000
+0d
+b000000 i
+#20240000
+1d
+#20267000
+xb
+0d
+b000001 i
+#20317000
+1d
+#20344000
+xb
+0d
+#20394000
+";
+
+    /// Capture of the first `pulses` pulses of a transmission, started 1.25s after the Unix
+    /// epoch.
+    fn capture(pulses: usize) -> Capture {
+        let mut capture = Capture::new(PulseUnit::Microseconds);
+        capture.metadata.timestamp = Some(UNIX_EPOCH + Duration::from_millis(1250));
+        capture.metadata.start_signal = Some(Duration::from_millis(20));
+        capture.pulses = Encoder::new()
+            .pulses(&[45, 0, 21, 3, 69])
+            .into_iter()
+            .take(pulses)
+            .map(|(level, duration)| (level, duration.as_micros() as u32))
+            .collect();
+        capture
+    }
+
+    fn dump(capture: &Capture) -> String {
+        let mut dump = Vec::new();
+        capture.write_vcd(&mut dump).unwrap();
+        String::from_utf8(dump).unwrap()
+    }
+
+    #[test]
+    fn writes_golden_dump() {
+        // Release, response low and high, a low and a high per bit and the low completing the
+        // last bit
+        assert_eq!(dump(&capture(3 + 2 * 2 + 1)), GOLDEN);
+    }
+
+    #[test]
+    fn annotates_decoded_bytes() {
+        let dump = dump(&capture(usize::MAX));
+        for byte in [
+            "b00101101 y",
+            "b00000000 y",
+            "b00010101 y",
+            "b00000011 y",
+            "b01000101 y",
+        ] {
+            assert!(dump.contains(byte), "missing {}", byte);
+        }
+        assert_eq!(dump.matches(" i\n").count(), 1 + DATA_BITS + 1);
+        // Only the initial value of the bit is unknown, then one value per bit, 12 of them set
+        assert_eq!(dump.matches("xb\n").count(), 1);
+        assert_eq!(dump.matches("1b\n").count(), 12);
+    }
+}